@@ -0,0 +1,163 @@
+use crate::codec::{Codec, encode};
+use crate::hasher::{Hasher, RollupHasher};
+use crate::merkle::{Hash, MerkleProof, hash_leaf, merkle_proof, merkle_root};
+use crate::signature::SignedTransaction;
+use crate::state::BlockContext;
+use crate::{Address, Balance, BlockNumber};
+
+/// What the first block names as its parent.
+pub const GENESIS_PARENT_HASH: Hash = [0u8; 32];
+
+/// Everything a block commits to, without the transactions themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub block_number: BlockNumber,
+    pub parent_hash: Hash,
+    pub sequencer: Address,
+    pub coinbase: Address,
+    pub base_fee: Balance,
+    pub timestamp: u64,  // L2 time the sequencer built the block at
+    pub tx_count: usize, // leaves under both the transactions and receipts roots
+    pub pre_state_root: Hash,
+    pub post_state_root: Hash,
+    pub state_roots_root: Hash, // over the state root after each transaction
+    pub transactions_root: Hash,
+    pub receipts_root: Hash,
+}
+
+impl BlockHeader {
+    /// Hash of the header's canonical encoding, which children name as their
+    /// parent.
+    pub fn hash(&self) -> Hash {
+        self.hash_with::<RollupHasher>()
+    }
+
+    /// [`BlockHeader::hash`] under a hasher other than the configured one.
+    pub fn hash_with<H: Hasher>(&self) -> Hash {
+        H::digest(&[&encode(self)])
+    }
+
+    /// What the block's transactions are executed against besides the state.
+    pub fn context(&self) -> BlockContext {
+        BlockContext {
+            coinbase: self.coinbase,
+            base_fee: self.base_fee,
+        }
+    }
+}
+
+/// Merkle root over `txs` in order, each leaf committing to a transaction's
+/// canonical encoding, signature included.
+pub fn transactions_root(txs: &[SignedTransaction]) -> Hash {
+    let leaves: Vec<Hash> = txs.iter().map(transaction_leaf).collect();
+    merkle_root(&leaves)
+}
+
+/// Proof that `txs[index]` is under `transactions_root(txs)`.
+pub fn transaction_proof(txs: &[SignedTransaction], index: usize) -> Option<MerkleProof> {
+    let leaves: Vec<Hash> = txs.iter().map(transaction_leaf).collect();
+    merkle_proof(&leaves, index)
+}
+
+pub fn transaction_leaf(tx: &SignedTransaction) -> Hash {
+    let mut bytes = vec![];
+    tx.write(&mut bytes);
+    hash_leaf(&bytes)
+}
+
+/// Merkle root over a block's intermediate state roots in order, so that
+/// each step a challenge or dispute is judged on is part of the block hash.
+pub fn state_roots_root(roots: &[Hash]) -> Hash {
+    let leaves: Vec<Hash> = roots.iter().map(state_root_leaf).collect();
+    merkle_root(&leaves)
+}
+
+/// Proof that `roots[index]` is under `state_roots_root(roots)`.
+pub fn state_root_proof(roots: &[Hash], index: usize) -> Option<MerkleProof> {
+    let leaves: Vec<Hash> = roots.iter().map(state_root_leaf).collect();
+    merkle_proof(&leaves, index)
+}
+
+pub fn state_root_leaf(root: &Hash) -> Hash {
+    hash_leaf(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::Keypair;
+    use crate::state::{NATIVE_ASSET, Transaction, TxKind};
+
+    fn header() -> BlockHeader {
+        BlockHeader {
+            block_number: 1,
+            parent_hash: [1; 32],
+            sequencer: 10,
+            coinbase: 5,
+            base_fee: 2,
+            timestamp: 100,
+            tx_count: 3,
+            pre_state_root: [2; 32],
+            post_state_root: [3; 32],
+            state_roots_root: [6; 32],
+            transactions_root: [4; 32],
+            receipts_root: [5; 32],
+        }
+    }
+
+    #[test]
+    fn test_hash_covers_every_header_field() {
+        let hash = header().hash();
+        let changes: [fn(&mut BlockHeader); 12] = [
+            |h| h.block_number += 1,
+            |h| h.parent_hash[0] ^= 1,
+            |h| h.sequencer += 1,
+            |h| h.coinbase += 1,
+            |h| h.base_fee += 1,
+            |h| h.timestamp += 1,
+            |h| h.tx_count += 1,
+            |h| h.pre_state_root[0] ^= 1,
+            |h| h.post_state_root[0] ^= 1,
+            |h| h.state_roots_root[0] ^= 1,
+            |h| h.transactions_root[0] ^= 1,
+            |h| h.receipts_root[0] ^= 1,
+        ];
+        for change in changes {
+            let mut changed = header();
+            change(&mut changed);
+            assert_ne!(changed.hash(), hash);
+        }
+    }
+
+    #[test]
+    fn test_transactions_root_commits_to_signatures_and_order() {
+        let key = Keypair::from_seed(&[1; 32]);
+        let txs: Vec<_> = (0..3)
+            .map(|nonce| {
+                key.sign(Transaction {
+                    kind: TxKind::Transfer,
+                    from: key.address(),
+                    to: 2,
+                    asset: NATIVE_ASSET,
+                    amount: 10,
+                    fee: 0,
+                    nonce,
+                })
+            })
+            .collect();
+        let root = transactions_root(&txs);
+        for (i, tx) in txs.iter().enumerate() {
+            let proof = transaction_proof(&txs, i).unwrap();
+            assert!(proof.verify(&root, &transaction_leaf(tx)));
+        }
+
+        let mut resigned = txs.clone();
+        resigned[1].signature = Keypair::from_seed(&[2; 32])
+            .sign(txs[1].tx.clone())
+            .signature;
+        assert_ne!(transactions_root(&resigned), root);
+
+        let reordered = [txs[1].clone(), txs[0].clone(), txs[2].clone()];
+        assert_ne!(transactions_root(&reordered), root);
+    }
+}
@@ -0,0 +1,784 @@
+use std::fmt;
+
+use crate::block::BlockHeader;
+use crate::fraud_proof::FraudProof;
+use crate::merkle::MerkleProof;
+use crate::receipt::Receipt;
+use crate::signature::SignedTransaction;
+use crate::smt::SmtProof;
+use crate::state::{AccountProof, BalanceProof, IssuerProof, State, Transaction, TxError, TxKind};
+use crate::verifier::{BlockStatus, FraudChallenge, RollupBlock};
+
+/// Format version written ahead of every encoded value.
+pub const CODEC_VERSION: u8 = 1;
+
+/// `TxError`s in declaration order, which is also their encoding.
+const TX_ERRORS: [TxError; 6] = [
+    TxError::BadSignature,
+    TxError::BadNonce,
+    TxError::FeeBelowBaseFee,
+    TxError::InsufficientBalance,
+    TxError::NotIssuer,
+    TxError::Overflow,
+];
+
+/// Why bytes didn't decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    TrailingBytes(usize),
+    InvalidTag {
+        field: &'static str,
+        tag: u8,
+    },
+    /// Well-formed, but not how the value would have been encoded.
+    NonCanonical(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported codec version {}", version)
+            }
+            DecodeError::UnexpectedEnd => write!(f, "input ends mid-value"),
+            DecodeError::TrailingBytes(count) => {
+                write!(f, "{} bytes left after the value", count)
+            }
+            DecodeError::InvalidTag { field, tag } => write!(f, "invalid {} tag {}", field, tag),
+            DecodeError::NonCanonical(field) => write!(f, "non-canonical {}", field),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Deterministic binary form of a value: integers big-endian, collections
+/// prefixed with a `u32` length, enums as a one-byte tag. Every value has
+/// exactly one encoding, so decoding is strict about what it accepts.
+pub trait Codec: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError>;
+}
+
+/// `value` behind the codec version, as published or hashed.
+pub fn encode<T: Codec>(value: &T) -> Vec<u8> {
+    let mut out = vec![CODEC_VERSION];
+    value.write(&mut out);
+    out
+}
+
+/// Inverse of `encode`, rejecting other versions and any bytes left over.
+pub fn decode<T: Codec>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let mut input = Reader { bytes };
+    let version = u8::read(&mut input)?;
+    if version != CODEC_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let value = T::read(&mut input)?;
+    if !input.bytes.is_empty() {
+        return Err(DecodeError::TrailingBytes(input.bytes.len()));
+    }
+    Ok(value)
+}
+
+/// Bytes not yet decoded.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let (taken, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(*taken)
+    }
+}
+
+impl Codec for u8 {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(input.take::<1>()?[0])
+    }
+}
+
+impl Codec for u64 {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(u64::from_be_bytes(input.take()?))
+    }
+}
+
+// As a `u64`, so the encoding doesn't depend on the platform
+impl Codec for usize {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u64).write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        usize::try_from(u64::read(input)?).map_err(|_| DecodeError::NonCanonical("usize"))
+    }
+}
+
+impl Codec for bool {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u8).write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        match u8::read(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(DecodeError::InvalidTag { field: "bool", tag }),
+        }
+    }
+}
+
+impl<const N: usize> Codec for [u8; N] {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        input.take()
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.is_some().write(out);
+        if let Some(value) = self {
+            value.write(out);
+        }
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        match bool::read(input)? {
+            true => T::read(input).map(Some),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.len() as u32).to_be_bytes());
+        for item in self {
+            item.write(out);
+        }
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let len = u32::from_be_bytes(input.take()?) as usize;
+        // Every item takes at least a byte, so a bogus length can't allocate much
+        let mut items = Vec::with_capacity(len.min(input.bytes.len()));
+        for _ in 0..len {
+            items.push(T::read(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Codec for TxKind {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u8).write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        match u8::read(input)? {
+            0 => Ok(TxKind::Transfer),
+            1 => Ok(TxKind::Mint),
+            2 => Ok(TxKind::Burn),
+            tag => Err(DecodeError::InvalidTag {
+                field: "tx kind",
+                tag,
+            }),
+        }
+    }
+}
+
+// Exactly the bytes its sender signs
+impl Codec for Transaction {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.encode());
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(Transaction {
+            kind: TxKind::read(input)?,
+            from: u64::read(input)?,
+            to: u64::read(input)?,
+            asset: u64::read(input)?,
+            amount: u64::read(input)?,
+            fee: u64::read(input)?,
+            nonce: u64::read(input)?,
+        })
+    }
+}
+
+impl Codec for SignedTransaction {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.tx.write(out);
+        self.public_key.write(out);
+        self.signature.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(SignedTransaction {
+            tx: Transaction::read(input)?,
+            public_key: Codec::read(input)?,
+            signature: Codec::read(input)?,
+        })
+    }
+}
+
+impl Codec for Receipt {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.encode().write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        match u8::read(input)? {
+            0 => Ok(Receipt::Success),
+            tag => TX_ERRORS
+                .get(tag as usize - 1)
+                .map(|&error| Receipt::Failed(error))
+                .ok_or(DecodeError::InvalidTag {
+                    field: "receipt",
+                    tag,
+                }),
+        }
+    }
+}
+
+impl Codec for BlockHeader {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.block_number.write(out);
+        self.parent_hash.write(out);
+        self.sequencer.write(out);
+        self.coinbase.write(out);
+        self.base_fee.write(out);
+        self.timestamp.write(out);
+        self.tx_count.write(out);
+        self.pre_state_root.write(out);
+        self.post_state_root.write(out);
+        self.state_roots_root.write(out);
+        self.transactions_root.write(out);
+        self.receipts_root.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(BlockHeader {
+            block_number: u64::read(input)?,
+            parent_hash: Codec::read(input)?,
+            sequencer: u64::read(input)?,
+            coinbase: u64::read(input)?,
+            base_fee: u64::read(input)?,
+            timestamp: u64::read(input)?,
+            tx_count: usize::read(input)?,
+            pre_state_root: Codec::read(input)?,
+            post_state_root: Codec::read(input)?,
+            state_roots_root: Codec::read(input)?,
+            transactions_root: Codec::read(input)?,
+            receipts_root: Codec::read(input)?,
+        })
+    }
+}
+
+// What the sequencer signed off on; when L1 accepted it and what became of it
+// are L1's bookkeeping and start over on decode
+impl Codec for RollupBlock {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.block_number.write(out);
+        self.parent_hash.write(out);
+        self.sequencer.write(out);
+        self.coinbase.write(out);
+        self.base_fee.write(out);
+        self.timestamp.write(out);
+        self.pre_state_root.write(out);
+        self.transactions.write(out);
+        self.aggregate_signature.write(out);
+        self.state_roots.write(out);
+        self.receipts.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(RollupBlock {
+            block_number: u64::read(input)?,
+            parent_hash: Codec::read(input)?,
+            sequencer: u64::read(input)?,
+            coinbase: u64::read(input)?,
+            base_fee: u64::read(input)?,
+            timestamp: u64::read(input)?,
+            pre_state_root: Codec::read(input)?,
+            transactions: Vec::read(input)?,
+            aggregate_signature: Codec::read(input)?,
+            state_roots: Vec::read(input)?,
+            receipts: Vec::read(input)?,
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        })
+    }
+}
+
+// Only the siblings the bitmap marks are present, so there's no length
+impl Codec for SmtProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.key.write(out);
+        self.bitmap.write(out);
+        for sibling in &self.siblings {
+            sibling.write(out);
+        }
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let key = u64::read(input)?;
+        let bitmap = u64::read(input)?;
+        let siblings = (0..bitmap.count_ones())
+            .map(|_| Codec::read(input))
+            .collect::<Result<_, _>>()?;
+        Ok(SmtProof {
+            key,
+            bitmap,
+            siblings,
+        })
+    }
+}
+
+impl Codec for MerkleProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.index.write(out);
+        self.leaf_count.write(out);
+        self.siblings.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(MerkleProof {
+            index: usize::read(input)?,
+            leaf_count: usize::read(input)?,
+            siblings: Vec::read(input)?,
+        })
+    }
+}
+
+impl Codec for BalanceProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.asset.write(out);
+        self.balance.write(out);
+        self.proof.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(BalanceProof {
+            asset: u64::read(input)?,
+            balance: u64::read(input)?,
+            proof: SmtProof::read(input)?,
+        })
+    }
+}
+
+impl Codec for AccountProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.address.write(out);
+        self.nonce.write(out);
+        self.assets_root.write(out);
+        self.proof.write(out);
+        self.balances.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(AccountProof {
+            address: u64::read(input)?,
+            nonce: u64::read(input)?,
+            assets_root: Codec::read(input)?,
+            proof: SmtProof::read(input)?,
+            balances: Vec::read(input)?,
+        })
+    }
+}
+
+impl Codec for IssuerProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.asset.write(out);
+        self.issuer.write(out);
+        self.proof.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(IssuerProof {
+            asset: u64::read(input)?,
+            issuer: Option::read(input)?,
+            proof: SmtProof::read(input)?,
+        })
+    }
+}
+
+impl Codec for FraudProof {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.tx.write(out);
+        self.tx_proof.write(out);
+        self.pre_state_root.write(out);
+        self.accounts_root.write(out);
+        self.issuers_root.write(out);
+        self.from_witness.write(out);
+        self.to_witness.write(out);
+        self.coinbase_witness.write(out);
+        self.issuer_witness.write(out);
+        self.post_state_root.write(out);
+        self.pre_state_proof.write(out);
+        self.post_state_proof.write(out);
+        self.receipt.write(out);
+        self.receipt_proof.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(FraudProof {
+            tx: SignedTransaction::read(input)?,
+            tx_proof: MerkleProof::read(input)?,
+            pre_state_root: Codec::read(input)?,
+            accounts_root: Codec::read(input)?,
+            issuers_root: Codec::read(input)?,
+            from_witness: AccountProof::read(input)?,
+            to_witness: AccountProof::read(input)?,
+            coinbase_witness: AccountProof::read(input)?,
+            issuer_witness: IssuerProof::read(input)?,
+            post_state_root: Codec::read(input)?,
+            pre_state_proof: Option::read(input)?,
+            post_state_proof: MerkleProof::read(input)?,
+            receipt: Receipt::read(input)?,
+            receipt_proof: MerkleProof::read(input)?,
+        })
+    }
+}
+
+// The verdict is L1's, so it isn't part of what a challenger submits
+impl Codec for FraudChallenge {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.block_number.write(out);
+        self.tx_index.write(out);
+        self.challenger.write(out);
+        self.time.write(out);
+        self.proof.write(out);
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(FraudChallenge {
+            block_number: u64::read(input)?,
+            tx_index: usize::read(input)?,
+            challenger: u64::read(input)?,
+            time: u64::read(input)?,
+            proof: FraudProof::read(input)?,
+            valid: None,
+        })
+    }
+}
+
+/// Every entry in key order, so equal states encode equally no matter how
+/// their maps were filled. Zero balances and nonces are the same as absent
+/// ones and left out; an issuer is an address, so address 0 is kept.
+impl Codec for State {
+    fn write(&self, out: &mut Vec<u8>) {
+        let balances = self.balances.iter().map(|(&(a, b), &v)| ([a, b], v));
+        write_entries(out, balances.filter(|&(_, v)| v != 0));
+        let nonces = self.nonces.iter().map(|(&k, &v)| ([k], v));
+        write_entries(out, nonces.filter(|&(_, v)| v != 0));
+        write_entries(out, self.issuers.iter().map(|(&k, &v)| ([k], v)));
+    }
+
+    fn read(input: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(State {
+            balances: read_entries(input, "balances", false)?
+                .into_iter()
+                .map(|([address, asset], balance)| ((address, asset), balance))
+                .collect(),
+            nonces: read_entries(input, "nonces", false)?
+                .into_iter()
+                .map(|([k], v)| (k, v))
+                .collect(),
+            issuers: read_entries(input, "issuers", true)?
+                .into_iter()
+                .map(|([k], v)| (k, v))
+                .collect(),
+        })
+    }
+}
+
+fn write_entries<const N: usize>(
+    out: &mut Vec<u8>,
+    entries: impl Iterator<Item = ([u64; N], u64)>,
+) {
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_unstable();
+    out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for (key, value) in entries {
+        for part in key {
+            part.write(out);
+        }
+        value.write(out);
+    }
+}
+
+fn read_entries<const N: usize>(
+    input: &mut Reader<'_>,
+    field: &'static str,
+    allow_zero: bool,
+) -> Result<Vec<([u64; N], u64)>, DecodeError> {
+    let len = u32::from_be_bytes(input.take()?) as usize;
+    let mut entries: Vec<([u64; N], u64)> = Vec::with_capacity(len.min(input.bytes.len()));
+    for _ in 0..len {
+        let mut key = [0; N];
+        for part in &mut key {
+            *part = u64::read(input)?;
+        }
+        let value = u64::read(input)?;
+        let ascending = entries.last().is_none_or(|(last, _)| *last < key);
+        if (value == 0 && !allow_zero) || !ascending {
+            return Err(DecodeError::NonCanonical(field));
+        }
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::aggregate_signatures;
+    use crate::state::NATIVE_ASSET;
+    use crate::test_utils::{CONTEXT, key};
+
+    fn setup() -> (State, RollupBlock) {
+        let alice = key(1);
+        let mut state = State::new();
+        state.balances.insert((alice.address(), NATIVE_ASSET), 100);
+        state.balances.insert((alice.address(), 7), 3);
+        state.balances.insert((9, NATIVE_ASSET), 0);
+        state.nonces.insert(alice.address(), 2);
+        state.issuers.insert(7, alice.address());
+
+        let transactions: Vec<_> = [(40, 2), (1000, 3)]
+            .map(|(amount, nonce)| {
+                alice.sign(Transaction {
+                    kind: TxKind::Transfer,
+                    from: alice.address(),
+                    to: 2,
+                    asset: NATIVE_ASSET,
+                    amount,
+                    fee: 1,
+                    nonce,
+                })
+            })
+            .into();
+        let mut post_state = state.clone();
+        let receipts: Vec<Receipt> = transactions
+            .iter()
+            .map(|tx| post_state.apply_signed_tx(tx, &CONTEXT).into())
+            .collect();
+        let block = RollupBlock {
+            block_number: 3,
+            parent_hash: [6; 32],
+            sequencer: 10,
+            coinbase: CONTEXT.coinbase,
+            base_fee: CONTEXT.base_fee,
+            timestamp: 11,
+            pre_state_root: state.root(),
+            aggregate_signature: aggregate_signatures(&transactions).unwrap(),
+            transactions,
+            state_roots: vec![post_state.root(); 2],
+            receipts,
+            submitted_at: 12,
+            status: BlockStatus::Finalized,
+        };
+        (state, block)
+    }
+
+    fn challenge() -> FraudChallenge {
+        let (state, block) = setup();
+        FraudChallenge {
+            block_number: block.block_number,
+            tx_index: 0,
+            challenger: 99,
+            time: 13,
+            proof: FraudProof::build(
+                &state,
+                &block.transactions,
+                &CONTEXT,
+                &block.state_roots,
+                &block.receipts,
+                0,
+            ),
+            valid: Some(false),
+        }
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let (state, block) = setup();
+        assert_eq!(
+            block.receipts[1],
+            Receipt::Failed(TxError::InsufficientBalance)
+        );
+
+        let tx = &block.transactions[0];
+        assert_eq!(encode(&tx.tx)[1..], tx.tx.encode());
+        assert_eq!(decode::<SignedTransaction>(&encode(tx)), Ok(tx.clone()));
+        let pending = RollupBlock {
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+            ..block.clone()
+        };
+        assert_eq!(decode::<RollupBlock>(&encode(&block)), Ok(pending.clone()));
+        assert_eq!(encode(&pending), encode(&block));
+        assert_eq!(
+            decode::<BlockHeader>(&encode(&block.header())),
+            Ok(block.header())
+        );
+        let unjudged = FraudChallenge {
+            valid: None,
+            ..challenge()
+        };
+        assert_eq!(
+            decode::<FraudChallenge>(&encode(&challenge())),
+            Ok(unjudged)
+        );
+
+        // Zero entries aren't part of the encoding, just as they aren't part of the root
+        let decoded = decode::<State>(&encode(&state)).unwrap();
+        assert_eq!(decoded.root(), state.root());
+        assert_eq!(decoded.balances.len(), 2);
+        assert_eq!(encode(&decoded), encode(&state));
+
+        for (i, error) in TX_ERRORS.iter().enumerate() {
+            assert_eq!(*error as usize, i);
+            let receipt = Receipt::Failed(*error);
+            assert_eq!(decode::<Receipt>(&encode(&receipt)), Ok(receipt));
+        }
+    }
+
+    fn assert_truncations_fail<T: Codec + fmt::Debug>(value: &T) {
+        let bytes = encode(value);
+        for len in 0..bytes.len() {
+            assert!(
+                decode::<T>(&bytes[..len]).is_err(),
+                "{len} of {} bytes",
+                bytes.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_truncated_and_padded_input() {
+        let (state, block) = setup();
+        assert_truncations_fail(&block);
+        assert_truncations_fail(&challenge());
+        assert_truncations_fail(&state);
+
+        let mut padded = encode(&block);
+        padded.push(0);
+        assert_eq!(
+            decode::<RollupBlock>(&padded),
+            Err(DecodeError::TrailingBytes(1))
+        );
+        assert_eq!(
+            decode::<u64>(&[CODEC_VERSION, 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_fields() {
+        let (_, block) = setup();
+
+        let mut bytes = encode(&block.transactions[0]);
+        bytes[0] = 2;
+        assert_eq!(
+            decode::<SignedTransaction>(&bytes),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+        bytes[0] = CODEC_VERSION;
+        bytes[1] = 3;
+        assert_eq!(
+            decode::<SignedTransaction>(&bytes),
+            Err(DecodeError::InvalidTag {
+                field: "tx kind",
+                tag: 3
+            })
+        );
+
+        let mut bytes = encode(&block);
+        *bytes.last_mut().unwrap() = 7;
+        assert_eq!(
+            decode::<RollupBlock>(&bytes),
+            Err(DecodeError::InvalidTag {
+                field: "receipt",
+                tag: 7
+            })
+        );
+        assert!(decode::<Receipt>(&[CODEC_VERSION, 7]).is_err());
+        assert!(decode::<Option<u8>>(&[CODEC_VERSION, 2, 0]).is_err());
+
+        // A length claiming more items than there are bytes
+        let mut bytes = encode(&vec![1u64, 2]);
+        bytes[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(decode::<Vec<u64>>(&bytes), Err(DecodeError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_state() {
+        let state_bytes = |balances: &[(u64, u64, u64)], nonces: &[(u64, u64)]| {
+            let mut out = vec![CODEC_VERSION];
+            out.extend_from_slice(&(balances.len() as u32).to_be_bytes());
+            for &(address, asset, balance) in balances {
+                [address, asset, balance]
+                    .iter()
+                    .for_each(|n| n.write(&mut out));
+            }
+            out.extend_from_slice(&(nonces.len() as u32).to_be_bytes());
+            for &(address, nonce) in nonces {
+                [address, nonce].iter().for_each(|n| n.write(&mut out));
+            }
+            out.extend_from_slice(&0u32.to_be_bytes()); // no issuers
+            out
+        };
+
+        let canonical = state_bytes(&[(1, 0, 5), (1, 7, 5), (2, 0, 5)], &[(1, 1)]);
+        let state = decode::<State>(&canonical).unwrap();
+        assert_eq!(encode(&state), canonical);
+
+        let unsorted = state_bytes(&[(2, 0, 5), (1, 0, 5)], &[]);
+        assert_eq!(
+            decode::<State>(&unsorted),
+            Err(DecodeError::NonCanonical("balances"))
+        );
+        let duplicate = state_bytes(&[(1, 0, 5), (1, 0, 6)], &[]);
+        assert_eq!(
+            decode::<State>(&duplicate),
+            Err(DecodeError::NonCanonical("balances"))
+        );
+        let zero = state_bytes(&[], &[(1, 0)]);
+        assert_eq!(
+            decode::<State>(&zero),
+            Err(DecodeError::NonCanonical("nonces"))
+        );
+    }
+
+    #[test]
+    fn test_issuer_zero_round_trips() {
+        let mut state = State::new();
+        state.issuers.insert(7, 0);
+        state.issuers.insert(8, 3);
+        let decoded = decode::<State>(&encode(&state)).unwrap();
+        assert_eq!(decoded.issuers, state.issuers);
+        assert_eq!(decoded.root(), state.root());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Address, BlockNumber};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Party {
+    Challenger,
+    Proposer,
+}
+
+impl Party {
+    pub fn opponent(self) -> Self {
+        match self {
+            Party::Challenger => Party::Proposer,
+            Party::Proposer => Party::Challenger,
+        }
+    }
+}
+
+/// Interactive bisection over the state roots of one block.
+///
+/// Roots are numbered by step: root 0 is the block's pre-state and root `i`
+/// is the state after `tx[i - 1]`. Both sides agree on root `agreed` and
+/// disagree on root `disputed`; each round the proposer restates its root at
+/// the midpoint and the challenger says whether it agrees, halving the range
+/// until a single transaction is left, which the proposer must then prove.
+#[derive(Clone, Debug)]
+pub struct Dispute {
+    pub block_number: BlockNumber,
+    pub challenger: Address,
+    pub agreed: usize,
+    pub disputed: usize,
+    pub turn: Party,
+    pub deadline: u64,
+    pub winner: Option<Party>,
+    pub cancelled: bool, // block was reverted by another challenge first
+}
+
+impl Dispute {
+    /// Disputes the whole block: the challenger accepts its pre-state but not
+    /// the root after its last transaction.
+    pub fn open(
+        block_number: BlockNumber,
+        challenger: Address,
+        tx_count: usize,
+        deadline: u64,
+    ) -> Self {
+        Self {
+            block_number,
+            challenger,
+            agreed: 0,
+            disputed: tx_count,
+            turn: Party::Proposer,
+            deadline,
+            winner: None,
+            cancelled: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.winner.is_none() && !self.cancelled
+    }
+
+    pub fn midpoint(&self) -> usize {
+        (self.agreed + self.disputed) / 2
+    }
+
+    /// Index of the one transaction left in dispute, once bisection is over.
+    pub fn disputed_tx(&self) -> Option<usize> {
+        (self.disputed - self.agreed == 1).then_some(self.agreed)
+    }
+
+    /// Proposer restated its midpoint root; the challenger answers next.
+    pub fn bisect(&mut self, deadline: u64) {
+        self.pass_turn(deadline);
+    }
+
+    /// Challenger narrows the range to the half it still disputes.
+    pub fn respond(&mut self, agrees_with_midpoint: bool, deadline: u64) {
+        let midpoint = self.midpoint();
+        if agrees_with_midpoint {
+            self.agreed = midpoint;
+        } else {
+            self.disputed = midpoint;
+        }
+        self.pass_turn(deadline);
+    }
+
+    fn pass_turn(&mut self, deadline: u64) {
+        self.turn = self.turn.opponent();
+        self.deadline = deadline;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisection_converges_to_single_step() {
+        // Challenger's view diverges from the proposer's starting at root 6 (tx[5])
+        let first_bad_root = 6;
+        let mut dispute = Dispute::open(0, 99, 13, 5);
+        let mut rounds = 0;
+
+        while dispute.disputed_tx().is_none() {
+            assert_eq!(dispute.turn, Party::Proposer);
+            dispute.bisect(5);
+            assert_eq!(dispute.turn, Party::Challenger);
+            dispute.respond(dispute.midpoint() < first_bad_root, 5);
+            rounds += 1;
+        }
+
+        assert_eq!(dispute.disputed_tx(), Some(5));
+        assert_eq!(dispute.turn, Party::Proposer);
+        assert!(rounds <= 4); // ceil(log2(13))
+    }
+
+    #[test]
+    fn test_single_tx_block_starts_at_final_step() {
+        let dispute = Dispute::open(3, 99, 1, 5);
+        assert_eq!(dispute.disputed_tx(), Some(0));
+        assert_eq!(dispute.turn, Party::Proposer);
+        assert!(dispute.is_active());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::Balance;
+
+/// Largest step the base fee takes between blocks, as a fraction of itself.
+pub const BASE_FEE_CHANGE_DENOMINATOR: Balance = 8;
+
+/// EIP-1559 style pricing of block space, with every transaction taking one
+/// unit of capacity.
+///
+/// Each block's base fee follows from its parent's: up when the parent held
+/// more than `target_txs` transactions, down when it held fewer, by at most
+/// 1/`BASE_FEE_CHANGE_DENOMINATOR`. Blocks may hold up to twice the target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeMarket {
+    pub initial_base_fee: Balance, // base fee of the first block
+    pub target_txs: usize,
+}
+
+impl Default for FeeMarket {
+    fn default() -> Self {
+        Self {
+            initial_base_fee: 0,
+            target_txs: 16,
+        }
+    }
+}
+
+impl FeeMarket {
+    pub fn max_txs(&self) -> usize {
+        self.target_txs.saturating_mul(2)
+    }
+
+    /// Base fee of a block whose parent had `parent_base_fee` and
+    /// `parent_tx_count` transactions. Computed in u128 so large fees can't
+    /// overflow, and capped at `Balance::MAX`.
+    pub fn next_base_fee(&self, parent_base_fee: Balance, parent_tx_count: usize) -> Balance {
+        let target = self.target_txs as u128;
+        let used = parent_tx_count as u128;
+        let base_fee = parent_base_fee as u128;
+        let denominator = BASE_FEE_CHANGE_DENOMINATOR as u128;
+        if used > target {
+            let delta = base_fee * (used - target) / target / denominator;
+            let next = base_fee + delta.max(1); // a zero base fee can still rise
+            next.min(Balance::MAX as u128) as Balance
+        } else {
+            let delta = base_fee * (target - used) / target / denominator;
+            (base_fee - delta) as Balance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_fee_tracks_block_fullness() {
+        let market = FeeMarket {
+            initial_base_fee: 800,
+            target_txs: 4,
+        };
+        assert_eq!(market.max_txs(), 8);
+
+        assert_eq!(market.next_base_fee(800, 4), 800);
+        assert_eq!(market.next_base_fee(800, 8), 900); // full block: +1/8
+        assert_eq!(market.next_base_fee(800, 6), 850);
+        assert_eq!(market.next_base_fee(800, 0), 700); // empty block: -1/8
+        assert_eq!(market.next_base_fee(800, 2), 750);
+    }
+
+    #[test]
+    fn test_base_fee_rises_from_zero_and_never_goes_negative() {
+        let market = FeeMarket {
+            initial_base_fee: 0,
+            target_txs: 4,
+        };
+        assert_eq!(market.next_base_fee(0, 5), 1);
+        assert_eq!(market.next_base_fee(0, 0), 0);
+
+        let mut base_fee = 5;
+        for _ in 0..100 {
+            base_fee = market.next_base_fee(base_fee, 0);
+        }
+        assert_eq!(base_fee, 5); // 1/8 of a small fee rounds down to nothing
+    }
+
+    #[test]
+    fn test_large_base_fees_saturate() {
+        let market = FeeMarket {
+            initial_base_fee: Balance::MAX,
+            target_txs: 4,
+        };
+        assert_eq!(market.next_base_fee(Balance::MAX, 8), Balance::MAX);
+        assert_eq!(
+            market.next_base_fee(Balance::MAX, 0),
+            Balance::MAX - Balance::MAX / 8
+        );
+        assert_eq!(market.next_base_fee(Balance::MAX - 1, 5), Balance::MAX);
+    }
+}
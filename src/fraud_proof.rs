@@ -0,0 +1,669 @@
+use std::collections::HashMap;
+
+use crate::Address;
+use crate::block::{
+    BlockHeader, state_root_leaf, state_root_proof, transaction_leaf, transaction_proof,
+};
+use crate::merkle::{Hash, MerkleProof};
+use crate::receipt::{Receipt, receipt_proof};
+use crate::signature::SignedTransaction;
+use crate::smt::SparseMerkleTree;
+use crate::state::{
+    AccountProof, BlockContext, IssuerProof, State, account_leaf, balance_leaf, issuer_leaf,
+    state_root,
+};
+
+/// Outcome of checking one transaction step against its witnesses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The claimed post-root and receipt are what the transaction produces.
+    Valid,
+    /// The transaction produces a different post-root or receipt. A failing
+    /// one (bad signature, stale or skipped nonce, overflow, minting or
+    /// burning without being the issuer...) must leave the root unchanged and
+    /// have a receipt with the reason it failed.
+    Fraud,
+    /// The witnesses don't match the transaction or the pre-root, so nothing was proven.
+    InvalidWitness,
+}
+
+/// Everything L1 needs to re-execute a single transaction: the transaction
+/// itself, proven to be in the block, the accounts it touches (sender,
+/// recipient and the coinbase collecting its tip) with the balances it reads,
+/// and the issuer of the asset it moves, all proven against the pre-state
+/// root, plus the post-root and receipt the sequencer claimed for it. The
+/// pre- and post-roots are proven to be the block's own roots around the
+/// transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FraudProof {
+    pub tx: SignedTransaction,
+    pub tx_proof: MerkleProof, // against the block's transactions root
+    pub pre_state_root: Hash,
+    pub accounts_root: Hash, // the two halves of `pre_state_root`
+    pub issuers_root: Hash,
+    pub from_witness: AccountProof,
+    pub to_witness: AccountProof,
+    pub coinbase_witness: AccountProof,
+    pub issuer_witness: IssuerProof,
+    pub post_state_root: Hash,
+    pub pre_state_proof: Option<MerkleProof>, // against the block's state roots root; tx[0] starts from its pre-state root
+    pub post_state_proof: MerkleProof,
+    pub receipt: Receipt,
+    pub receipt_proof: MerkleProof, // against the block's receipts root
+}
+
+impl FraudProof {
+    /// Builds the proof for `transactions[tx_index]` from the full L2 state
+    /// before it, as a challenger replaying the chain would, and the state
+    /// roots and receipts the block claims.
+    pub fn build(
+        pre_state: &State,
+        transactions: &[SignedTransaction],
+        block: &BlockContext,
+        claimed_state_roots: &[Hash],
+        claimed_receipts: &[Receipt],
+        tx_index: usize,
+    ) -> Self {
+        let tx = &transactions[tx_index];
+        let [from_assets, to_assets, coinbase_assets] = tx.tx.touched_assets();
+        Self {
+            tx: tx.clone(),
+            tx_proof: transaction_proof(transactions, tx_index).expect("tx_index is in range"),
+            pre_state_root: pre_state.root(),
+            accounts_root: pre_state.accounts_root(),
+            issuers_root: pre_state.issuers_root(),
+            from_witness: pre_state.prove(tx.tx.from, &from_assets),
+            to_witness: pre_state.prove(tx.tx.to, &to_assets),
+            coinbase_witness: pre_state.prove(block.coinbase, &coinbase_assets),
+            issuer_witness: pre_state.prove_issuer(tx.tx.asset),
+            post_state_root: claimed_state_roots[tx_index],
+            pre_state_proof: tx_index
+                .checked_sub(1)
+                .and_then(|index| state_root_proof(claimed_state_roots, index)),
+            post_state_proof: state_root_proof(claimed_state_roots, tx_index)
+                .expect("tx_index is within claimed_state_roots"),
+            receipt: claimed_receipts[tx_index],
+            receipt_proof: receipt_proof(claimed_receipts, tx_index)
+                .expect("tx_index is within claimed_receipts"),
+        }
+    }
+
+    /// Whether the transaction, its claimed receipt and the roots around it
+    /// are the ones at `tx_index` in the block `header` commits to. Every
+    /// proof must span the header's transaction count: a proof over fewer
+    /// leaves can pass off an inner node as a leaf's sibling and place a
+    /// later leaf at `tx_index`.
+    pub fn proves_inclusion(&self, header: &BlockHeader, tx_index: usize) -> bool {
+        self.tx_proof.index == tx_index
+            && self.tx_proof.leaf_count == header.tx_count
+            && self.receipt_proof.leaf_count == header.tx_count
+            && self
+                .tx_proof
+                .verify(&header.transactions_root, &transaction_leaf(&self.tx))
+            && self.proves_receipt(&header.receipts_root, tx_index)
+            && self.proves_state_roots(header, tx_index)
+    }
+
+    /// Whether the pre- and post-roots are the block's roots before and after
+    /// `tx_index`.
+    pub fn proves_state_roots(&self, header: &BlockHeader, tx_index: usize) -> bool {
+        let proves = |proof: &MerkleProof, index: usize, root: &Hash| {
+            proof.index == index
+                && proof.leaf_count == header.tx_count
+                && proof.verify(&header.state_roots_root, &state_root_leaf(root))
+        };
+        let pre_root_proven = match (tx_index.checked_sub(1), &self.pre_state_proof) {
+            (None, None) => self.pre_state_root == header.pre_state_root,
+            (Some(index), Some(proof)) => proves(proof, index, &self.pre_state_root),
+            _ => false,
+        };
+        pre_root_proven && proves(&self.post_state_proof, tx_index, &self.post_state_root)
+    }
+
+    /// Whether the claimed receipt is the one at `tx_index` under `receipts_root`.
+    pub fn proves_receipt(&self, receipts_root: &Hash, tx_index: usize) -> bool {
+        self.receipt_proof.index == tx_index
+            && self
+                .receipt_proof
+                .verify(receipts_root, &self.receipt.leaf())
+    }
+
+    /// Re-executes the transaction, as included in `block`, over the witnessed
+    /// accounts only and compares the resulting root and receipt with the
+    /// claimed ones.
+    pub fn verify(&self, block: &BlockContext) -> Verdict {
+        let tx = &self.tx;
+        let witnesses = [&self.from_witness, &self.to_witness, &self.coinbase_witness];
+        let expected = [tx.tx.from, tx.tx.to, block.coinbase]
+            .into_iter()
+            .zip(tx.tx.touched_assets());
+        for (witness, (address, assets)) in witnesses.iter().zip(expected) {
+            let witnessed_assets: Vec<_> = witness.balances.iter().map(|b| b.asset).collect();
+            if witness.address != address || witnessed_assets != assets {
+                return Verdict::InvalidWitness;
+            }
+        }
+        if self.issuer_witness.asset != tx.tx.asset
+            || state_root(&self.accounts_root, &self.issuers_root) != self.pre_state_root
+            || !self.issuer_witness.verify(&self.issuers_root)
+        {
+            return Verdict::InvalidWitness;
+        }
+        let Some(mut accounts) = <SparseMerkleTree>::from_witnesses(
+            &self.accounts_root,
+            witnesses.map(|witness| {
+                let leaf = account_leaf(witness.address, witness.nonce, &witness.assets_root);
+                (&witness.proof, leaf)
+            }),
+        ) else {
+            return Verdict::InvalidWitness;
+        };
+        // The same account may be witnessed in several roles
+        let mut assets: HashMap<Address, SparseMerkleTree> = HashMap::new();
+        for witness in witnesses {
+            if assets.contains_key(&witness.address) {
+                continue;
+            }
+            let balances = witnesses
+                .iter()
+                .filter(|other| other.address == witness.address)
+                .flat_map(|other| &other.balances)
+                .map(|b| (&b.proof, balance_leaf(b.asset, b.balance)));
+            let Some(tree) = SparseMerkleTree::from_witnesses(&witness.assets_root, balances)
+            else {
+                return Verdict::InvalidWitness;
+            };
+            assets.insert(witness.address, tree);
+        }
+
+        // The witnessed balances, nonces and issuer are all `apply_tx` can read or write
+        let mut state = State::new();
+        for witness in witnesses {
+            for balance in &witness.balances {
+                state
+                    .balances
+                    .insert((witness.address, balance.asset), balance.balance);
+            }
+            state.nonces.insert(witness.address, witness.nonce);
+        }
+        if let Some(issuer) = self.issuer_witness.issuer {
+            state.issuers.insert(tx.tx.asset, issuer);
+        }
+        // A failed transaction leaves `state` as witnessed, so the root below stays put
+        if Receipt::from(state.apply_signed_tx(tx, block)) != self.receipt {
+            return Verdict::Fraud;
+        }
+
+        for witness in witnesses {
+            let tree = assets.get_mut(&witness.address).expect("built above");
+            for balance in &witness.balances {
+                let leaf =
+                    balance_leaf(balance.asset, state.balance(witness.address, balance.asset));
+                tree.update(balance.asset, leaf);
+            }
+        }
+        accounts.update_batch(assets.iter().map(|(&address, tree)| {
+            (
+                address,
+                account_leaf(address, state.nonce(address), &tree.root()),
+            )
+        }));
+        let mut issuers = <SparseMerkleTree>::from_witnesses(
+            &self.issuers_root,
+            [(
+                &self.issuer_witness.proof,
+                issuer_leaf(tx.tx.asset, self.issuer_witness.issuer),
+            )],
+        )
+        .expect("verified above");
+        issuers.update(
+            tx.tx.asset,
+            issuer_leaf(tx.tx.asset, state.issuer(tx.tx.asset)),
+        );
+
+        if state_root(&accounts.root(), &issuers.root()) == self.post_state_root {
+            Verdict::Valid
+        } else {
+            Verdict::Fraud
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Balance;
+    use crate::state::{NATIVE_ASSET, Transaction, TxError, TxKind};
+    use crate::test_utils::{COINBASE, CONTEXT, addr, key, setup_state, transfer};
+
+    fn post_root(pre_state: &State, tx: &SignedTransaction) -> Hash {
+        let mut state = pre_state.clone();
+        let _ = state.apply_signed_tx(tx, &CONTEXT);
+        state.root()
+    }
+
+    #[test]
+    fn test_honest_transition_verifies() {
+        let state = setup_state();
+        for tx in [
+            transfer(1, 2, 40, 0),
+            transfer(2, 3, 50, 0), // recipient doesn't exist yet
+            transfer(9, 9, 7, 0),
+        ] {
+            let proof = FraudProof::build(
+                &state,
+                std::slice::from_ref(&tx),
+                &CONTEXT,
+                &[post_root(&state, &tx)],
+                &[Receipt::Success],
+                0,
+            );
+            assert_eq!(proof.verify(&CONTEXT), Verdict::Valid);
+        }
+    }
+
+    #[test]
+    fn test_wrong_post_root_is_fraud() {
+        let state = setup_state();
+        let tx = transfer(1, 3, 40, 0);
+        let mut forged = state.clone();
+        let _ = forged.apply_signed_tx(&tx, &CONTEXT);
+        forged.balances.insert((addr(3), NATIVE_ASSET), 400);
+
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[forged.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+    }
+
+    #[test]
+    fn test_failing_transaction_needs_failure_receipt() {
+        let state = setup_state();
+        let tx = transfer(3, 1, 1, 0); // sender proven not to exist
+        let failed = [Receipt::Failed(TxError::InsufficientBalance)];
+
+        // A no-op with the reason it failed is the honest outcome
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[state.root()],
+            &failed,
+            0,
+        );
+        assert_eq!(proof.from_witness.balances[0].balance, 0);
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Valid);
+
+        // Claiming it succeeded, or failed for another reason
+        for receipt in [Receipt::Success, Receipt::Failed(TxError::BadNonce)] {
+            let proof = FraudProof::build(
+                &state,
+                std::slice::from_ref(&tx),
+                &CONTEXT,
+                &[state.root()],
+                &[receipt],
+                0,
+            );
+            assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+        }
+
+        // Failing but still touching the state, e.g. using up the nonce
+        let mut changed = state.clone();
+        changed.nonces.insert(addr(3), 1);
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[changed.root()],
+            &failed,
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+
+        // Dropping a transaction that would have succeeded
+        let ok = transfer(1, 2, 40, 0);
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&ok),
+            &CONTEXT,
+            &[state.root()],
+            &failed,
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+    }
+
+    #[test]
+    fn test_forged_witness_is_rejected() {
+        let state = setup_state();
+        let tx = transfer(2, 1, 80, 0);
+
+        // Claim the sender had enough to cover the transfer
+        let mut proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[post_root(&state, &tx)],
+            &[Receipt::Success],
+            0,
+        );
+        proof.from_witness.balances[0].balance = 80;
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+
+        // Witnesses for a different transaction
+        let other = transfer(9, 1, 1, 0);
+        let mut proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&other),
+            &CONTEXT,
+            &[post_root(&state, &other)],
+            &[Receipt::Success],
+            0,
+        );
+        proof.tx = tx.clone();
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+
+        // Claim the sender hadn't used its nonce yet
+        let mut state = setup_state();
+        state.nonces.insert(addr(2), 1);
+        let mut proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[post_root(&state, &tx)],
+            &[Receipt::Success],
+            0,
+        );
+        proof.from_witness.nonce = 0;
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+    }
+
+    #[test]
+    fn test_replayed_transaction_is_fraud() {
+        let state = setup_state();
+        let tx = transfer(1, 2, 40, 0);
+        let mut after_tx = state.clone();
+        assert!(after_tx.apply_signed_tx(&tx, &CONTEXT).is_ok());
+
+        // Sequencer runs the same transfer again, ignoring the spent nonce
+        let mut replayed = after_tx.clone();
+        replayed.balances.insert((addr(1), NATIVE_ASSET), 20);
+        replayed.balances.insert((addr(2), NATIVE_ASSET), 130);
+
+        let proof = FraudProof::build(
+            &after_tx,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[replayed.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.from_witness.nonce, 1);
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+    }
+
+    #[test]
+    fn test_unsigned_transaction_is_fraud() {
+        let state = setup_state();
+
+        // Sequencer moves funds the sender never signed off on
+        let mut tx = transfer(1, 2, 40, 0);
+        tx.tx.amount = 100;
+        let mut post_state = state.clone();
+        assert!(post_state.apply_tx(&tx.tx, &CONTEXT).is_ok());
+
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[post_state.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+
+        // Same for a signature by someone other than the sender
+        let stolen = key(2).sign(tx.tx.clone());
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&stolen),
+            &CONTEXT,
+            &[post_state.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+    }
+
+    #[test]
+    fn test_fee_must_reach_coinbase() {
+        let state = setup_state();
+        let tx = key(1).sign(Transaction {
+            kind: TxKind::Transfer,
+            from: addr(1),
+            to: addr(2),
+            asset: NATIVE_ASSET,
+            amount: 40,
+            fee: 3,
+            nonce: 0,
+        });
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[post_root(&state, &tx)],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.coinbase_witness.balances[0].balance, 0);
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Valid);
+
+        // Sequencer credits itself more than the fee
+        let mut skimmed = state.clone();
+        let _ = skimmed.apply_signed_tx(&tx, &CONTEXT);
+        skimmed.balances.insert((COINBASE, NATIVE_ASSET), 30);
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[skimmed.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+
+        // Witnessing some other account as the coinbase proves nothing
+        let elsewhere = BlockContext {
+            coinbase: addr(9),
+            ..CONTEXT
+        };
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &elsewhere,
+            &[post_root(&state, &tx)],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+    }
+
+    #[test]
+    fn test_base_fee_is_burned() {
+        let state = setup_state();
+        let block = BlockContext {
+            coinbase: COINBASE,
+            base_fee: 2,
+        };
+        let tx = |fee| {
+            key(1).sign(Transaction {
+                kind: TxKind::Transfer,
+                from: addr(1),
+                to: addr(2),
+                asset: NATIVE_ASSET,
+                amount: 40,
+                fee,
+                nonce: 0,
+            })
+        };
+
+        // Only the tip above the base fee reaches the coinbase
+        let mut post_state = state.clone();
+        assert!(post_state.apply_signed_tx(&tx(3), &block).is_ok());
+        assert_eq!(post_state.balance(addr(1), NATIVE_ASSET), 57);
+        assert_eq!(post_state.balance(COINBASE, NATIVE_ASSET), 1);
+        let proof = FraudProof::build(
+            &state,
+            &[tx(3)],
+            &block,
+            &[post_state.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&block), Verdict::Valid);
+
+        // Sequencer keeping the base fee too
+        let mut unburned = post_state.clone();
+        unburned.balances.insert((COINBASE, NATIVE_ASSET), 3);
+        let proof = FraudProof::build(
+            &state,
+            &[tx(3)],
+            &block,
+            &[unburned.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&block), Verdict::Fraud);
+
+        // Including a transaction that doesn't pay the base fee
+        let mut underpaid = state.clone();
+        assert!(underpaid.apply_signed_tx(&tx(1), &CONTEXT).is_ok());
+        let proof = FraudProof::build(
+            &state,
+            &[tx(1)],
+            &block,
+            &[underpaid.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&block), Verdict::Fraud);
+    }
+
+    #[test]
+    fn test_mint_without_issuing_rights_is_fraud() {
+        let state = setup_state();
+        let token = 7;
+        let mint = |from: u8, nonce| {
+            key(from).sign(Transaction {
+                kind: TxKind::Mint,
+                from: addr(from),
+                to: addr(2),
+                asset: token,
+                amount: 30,
+                fee: 0,
+                nonce,
+            })
+        };
+
+        // Issuing a new asset, then moving it, re-executes honestly
+        let first = mint(1, 0);
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&first),
+            &CONTEXT,
+            &[post_root(&state, &first)],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.issuer_witness.issuer, None);
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Valid);
+
+        let mut issued = state.clone();
+        assert!(issued.apply_signed_tx(&first, &CONTEXT).is_ok());
+        let transfer = key(2).sign(Transaction {
+            kind: TxKind::Transfer,
+            from: addr(2),
+            to: addr(9),
+            asset: token,
+            amount: 10,
+            fee: 0,
+            nonce: 0,
+        });
+        let proof = FraudProof::build(
+            &issued,
+            std::slice::from_ref(&transfer),
+            &CONTEXT,
+            &[post_root(&issued, &transfer)],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.from_witness.balances.len(), 2); // token and native for the fee
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Valid);
+
+        // Someone else minting more of it
+        let usurper = mint(9, 0);
+        let mut inflated = issued.clone();
+        inflated.balances.insert((addr(2), token), 60);
+        let proof = FraudProof::build(
+            &issued,
+            std::slice::from_ref(&usurper),
+            &CONTEXT,
+            &[inflated.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+
+        // Hiding the issuer doesn't verify against the pre-state
+        let mut proof = FraudProof::build(
+            &issued,
+            std::slice::from_ref(&usurper),
+            &CONTEXT,
+            &[inflated.root()],
+            &[Receipt::Success],
+            0,
+        );
+        proof.issuer_witness.issuer = None;
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+
+        // Nor does leaving out a balance the transaction reads
+        let mut proof = FraudProof::build(
+            &issued,
+            std::slice::from_ref(&transfer),
+            &CONTEXT,
+            &[state.root()],
+            &[Receipt::Success],
+            0,
+        );
+        proof.from_witness.balances.pop();
+        assert_eq!(proof.verify(&CONTEXT), Verdict::InvalidWitness);
+    }
+
+    #[test]
+    fn test_wrapped_balance_is_fraud() {
+        let mut state = setup_state();
+        state.balances.insert((addr(2), NATIVE_ASSET), Balance::MAX);
+        let tx = transfer(1, 2, 40, 0);
+
+        // Sequencer built in release mode and let the recipient wrap around
+        let mut wrapped = state.clone();
+        wrapped.balances.insert((addr(1), NATIVE_ASSET), 60);
+        wrapped.balances.insert((addr(2), NATIVE_ASSET), 39);
+        wrapped.nonces.insert(addr(1), 1);
+
+        let proof = FraudProof::build(
+            &state,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &[wrapped.root()],
+            &[Receipt::Success],
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+    }
+}
@@ -0,0 +1,130 @@
+use sha2::Digest;
+
+use crate::merkle::Hash;
+
+/// A 32-byte hash function for the rollup's commitments: state, transaction
+/// and receipt roots, and block hashes.
+pub trait Hasher {
+    /// Hash of `parts` concatenated, without copying them together first.
+    fn digest(parts: &[&[u8]]) -> Hash;
+}
+
+/// SHA-256, the default.
+pub enum Sha256 {}
+
+impl Hasher for Sha256 {
+    fn digest(parts: &[&[u8]]) -> Hash {
+        let mut hasher = sha2::Sha256::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Keccak-256 as the EVM computes it, i.e. before NIST's padding change.
+pub enum Keccak256 {}
+
+impl Hasher for Keccak256 {
+    fn digest(parts: &[&[u8]]) -> Hash {
+        let mut hasher = sha3::Keccak256::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// What every commitment in the crate is hashed with, chosen at build time
+/// by the `keccak` feature.
+#[cfg(not(feature = "keccak"))]
+pub type RollupHasher = Sha256;
+#[cfg(feature = "keccak")]
+pub type RollupHasher = Keccak256;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockHeader;
+    use crate::merkle::{hash_leaf, hash_leaf_with, merkle_root, merkle_root_with};
+    use crate::smt::SparseMerkleTree;
+
+    fn hex(hash: Hash) -> String {
+        hash.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hex(Sha256::digest(&[b"abc"])),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(Keccak256::digest(&[])),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(Keccak256::digest(&[b"abc"])),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn test_parts_hash_as_their_concatenation() {
+        assert_eq!(
+            Sha256::digest(&[b"ab", b"", b"c"]),
+            Sha256::digest(&[b"abc"])
+        );
+        assert_eq!(
+            Keccak256::digest(&[b"a", b"bc"]),
+            Keccak256::digest(&[b"abc"])
+        );
+    }
+
+    fn header(transactions_root: Hash, post_state_root: Hash) -> BlockHeader {
+        BlockHeader {
+            block_number: 1,
+            parent_hash: [0; 32],
+            sequencer: 10,
+            coinbase: 5,
+            base_fee: 1,
+            timestamp: 0,
+            tx_count: 4,
+            pre_state_root: [0; 32],
+            post_state_root,
+            state_roots_root: [0; 32],
+            transactions_root,
+            receipts_root: [0; 32],
+        }
+    }
+
+    /// The transactions root, state root and header hash of the same block
+    /// contents under `H`.
+    fn commitments<H: Hasher + 'static>() -> [Hash; 3] {
+        let leaves: Vec<Hash> = (0u8..4).map(|i| hash_leaf_with::<H>(&[i])).collect();
+        let transactions_root = merkle_root_with::<H>(&leaves);
+        let mut tree = SparseMerkleTree::<H>::with_hasher();
+        tree.update_batch(leaves.iter().zip(0..).map(|(leaf, key)| (key, Some(*leaf))));
+        let header = header(transactions_root, tree.root());
+        [transactions_root, tree.root(), header.hash_with::<H>()]
+    }
+
+    #[test]
+    fn test_commitments_under_either_hasher() {
+        let sha = commitments::<Sha256>();
+        let keccak = commitments::<Keccak256>();
+        for (sha, keccak) in sha.iter().zip(&keccak) {
+            assert_ne!(sha, keccak);
+        }
+
+        // The generic paths agree with what the rest of the crate commits to
+        let leaves: Vec<Hash> = (0u8..4).map(|i| hash_leaf(&[i])).collect();
+        let mut tree = SparseMerkleTree::new();
+        tree.update_batch(leaves.iter().zip(0..).map(|(leaf, key)| (key, Some(*leaf))));
+        let header = header(merkle_root(&leaves), tree.root());
+        assert_eq!(
+            commitments::<RollupHasher>(),
+            [merkle_root(&leaves), tree.root(), header.hash()]
+        );
+    }
+}
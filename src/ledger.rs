@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::{Address, Balance, BlockNumber};
+
+/// Share of a slashed bond paid to the party that won against it; the rest is burned.
+pub const SLASH_REWARD_PERCENT: Balance = 50;
+
+/// What a bond is securing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BondId {
+    Block(BlockNumber),
+    Challenge {
+        block_number: BlockNumber,
+        tx_index: usize,
+        challenger: Address,
+    },
+    Dispute(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bond {
+    pub owner: Address,
+    pub amount: Balance,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutKind {
+    Refund,
+    Reward,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Payout {
+    pub to: Address,
+    pub amount: Balance,
+    pub bond: BondId,
+    pub kind: PayoutKind,
+}
+
+/// L1 funds of sequencers and challengers: free balances, bonds locked
+/// against blocks and challenges, and a log of every payout.
+#[derive(Clone, Debug, Default)]
+pub struct Ledger {
+    balances: HashMap<Address, Balance>,
+    bonds: HashMap<BondId, Bond>,
+    payouts: Vec<Payout>,
+    burned: Balance,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Credits `amount` to `owner`. Fails if its funds, bonded ones included,
+    /// would no longer fit in a `Balance`, so refunds can't overflow either.
+    pub fn deposit(&mut self, owner: Address, amount: Balance) -> bool {
+        if self.room(owner) < amount {
+            return false;
+        }
+        *self.balances.entry(owner).or_default() += amount;
+        true
+    }
+
+    /// Funds not locked in any bond.
+    pub fn balance(&self, owner: Address) -> Balance {
+        self.balances.get(&owner).copied().unwrap_or(0)
+    }
+
+    pub fn bond(&self, id: BondId) -> Option<Bond> {
+        self.bonds.get(&id).copied()
+    }
+
+    pub fn payouts(&self) -> &[Payout] {
+        &self.payouts
+    }
+
+    pub fn burned(&self) -> Balance {
+        self.burned
+    }
+
+    /// Locks `amount` of `owner`'s funds under `id`. Fails if they can't
+    /// cover it or something is already bonded under `id`.
+    pub fn post_bond(&mut self, id: BondId, owner: Address, amount: Balance) -> bool {
+        let balance = self.balance(owner);
+        if balance < amount || self.bonds.contains_key(&id) {
+            return false;
+        }
+        self.balances.insert(owner, balance - amount);
+        self.bonds.insert(id, Bond { owner, amount });
+        true
+    }
+
+    /// Returns the bond to its owner.
+    pub fn refund(&mut self, id: BondId) {
+        if let Some(bond) = self.bonds.remove(&id) {
+            self.pay(bond.owner, bond.amount, id, PayoutKind::Refund);
+        }
+    }
+
+    /// Takes the bond from its owner, rewarding `winner` with
+    /// `SLASH_REWARD_PERCENT` of it and burning the rest, along with any part
+    /// of the reward the winner's funds have no room for.
+    pub fn slash(&mut self, id: BondId, winner: Address) {
+        if let Some(bond) = self.bonds.remove(&id) {
+            let reward = (bond.amount as u128 * SLASH_REWARD_PERCENT as u128 / 100) as Balance;
+            let reward = reward.min(self.room(winner));
+            self.pay(winner, reward, id, PayoutKind::Reward);
+            self.burned = self.burned.saturating_add(bond.amount - reward);
+        }
+    }
+
+    // How much more `owner` can hold, free or bonded
+    fn room(&self, owner: Address) -> Balance {
+        let bonded: u128 = self
+            .bonds
+            .values()
+            .filter(|bond| bond.owner == owner)
+            .map(|bond| bond.amount as u128)
+            .sum();
+        let held = self.balance(owner) as u128 + bonded;
+        (Balance::MAX as u128).saturating_sub(held) as Balance
+    }
+
+    fn pay(&mut self, to: Address, amount: Balance, bond: BondId, kind: PayoutKind) {
+        let balance = self.balances.entry(to).or_default();
+        *balance = balance
+            .checked_add(amount)
+            .expect("deposits leave room for every refund and rewards are capped");
+        self.payouts.push(Payout {
+            to,
+            amount,
+            bond,
+            kind,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_bond_requires_funds() {
+        let mut ledger = Ledger::new();
+        assert!(ledger.deposit(1, 100));
+
+        assert!(!ledger.post_bond(BondId::Block(0), 1, 101));
+        assert!(ledger.post_bond(BondId::Block(0), 1, 60));
+        assert!(!ledger.post_bond(BondId::Block(0), 1, 10)); // already bonded
+        assert_eq!(ledger.balance(1), 40);
+        assert_eq!(
+            ledger.bond(BondId::Block(0)),
+            Some(Bond {
+                owner: 1,
+                amount: 60
+            })
+        );
+    }
+
+    #[test]
+    fn test_refund_and_slash() {
+        let mut ledger = Ledger::new();
+        assert!(ledger.deposit(1, 100));
+        assert!(ledger.deposit(2, 10));
+        assert!(ledger.post_bond(BondId::Block(0), 1, 100));
+        assert!(ledger.post_bond(BondId::Dispute(0), 2, 10));
+
+        ledger.slash(BondId::Block(0), 2);
+        ledger.refund(BondId::Dispute(0));
+        ledger.slash(BondId::Block(0), 2); // already gone, no-op
+
+        assert_eq!(ledger.balance(1), 0);
+        assert_eq!(ledger.balance(2), 60);
+        assert_eq!(ledger.burned(), 50);
+        assert_eq!(
+            ledger.payouts(),
+            [
+                Payout {
+                    to: 2,
+                    amount: 50,
+                    bond: BondId::Block(0),
+                    kind: PayoutKind::Reward,
+                },
+                Payout {
+                    to: 2,
+                    amount: 10,
+                    bond: BondId::Dispute(0),
+                    kind: PayoutKind::Refund,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_bonds_neither_overflow_nor_mint() {
+        let mut ledger = Ledger::new();
+        let bond = Balance::MAX - 1;
+        assert!(ledger.deposit(1, bond));
+        assert!(ledger.post_bond(BondId::Block(0), 1, bond));
+        assert!(!ledger.deposit(1, 2)); // its bond still counts
+        assert!(ledger.deposit(1, 1));
+
+        ledger.slash(BondId::Block(0), 2);
+        assert_eq!(ledger.balance(2), bond / 2);
+        assert_eq!(ledger.burned(), bond - bond / 2);
+
+        // A winner without room for the full reward gets what fits
+        assert!(ledger.deposit(1, Balance::MAX - 3));
+        assert!(ledger.deposit(3, 10));
+        assert!(ledger.post_bond(BondId::Dispute(0), 3, 10));
+        ledger.slash(BondId::Dispute(0), 1);
+        assert_eq!(ledger.balance(1), Balance::MAX);
+        assert_eq!(ledger.burned(), bond - bond / 2 + 8);
+    }
+}
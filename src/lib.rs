@@ -0,0 +1,42 @@
+pub mod block;
+pub mod codec;
+pub mod dispute;
+pub mod fee_market;
+pub mod fraud_proof;
+pub mod hasher;
+pub mod ledger;
+pub mod mempool;
+pub mod merkle;
+pub mod receipt;
+pub mod sequencer;
+pub mod signature;
+pub mod smt;
+pub mod state;
+pub mod verifier;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use block::{
+    BlockHeader, GENESIS_PARENT_HASH, state_root_proof, state_roots_root, transaction_proof,
+    transactions_root,
+};
+pub use codec::{Codec, DecodeError};
+pub use dispute::{Dispute, Party};
+pub use fee_market::FeeMarket;
+pub use fraud_proof::FraudProof;
+pub use hasher::{Hasher, Keccak256, RollupHasher, Sha256};
+pub use ledger::{BondId, Ledger};
+pub use mempool::{Mempool, MempoolError};
+pub use receipt::{Receipt, receipts_root};
+pub use sequencer::Sequencer;
+pub use signature::{Keypair, SignedTransaction, aggregate_signatures};
+pub use state::{BlockContext, NATIVE_ASSET, State, Transaction, TxError, TxKind};
+pub use verifier::{
+    BlockEvent, BlockStatus, FailedTxPolicy, FraudChallenge, L1Verifier, RollupBlock, VerifierError,
+};
+
+pub type Address = u64;
+pub type AssetId = u64;
+pub type Balance = u64;
+pub type BlockNumber = u64;
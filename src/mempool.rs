@@ -0,0 +1,472 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
+
+use crate::signature::SignedTransaction;
+use crate::state::State;
+use crate::{Address, Balance};
+
+/// How much a replacement must raise the fee of the transaction it replaces,
+/// in percent, so a pool can't be churned for free.
+pub const REPLACEMENT_FEE_BUMP_PERCENT: Balance = 10;
+
+/// Why a transaction was turned away from the pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MempoolError {
+    BadSignature,
+    StaleNonce,
+    ReplacementUnderpriced,
+    PoolFull,
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::BadSignature => write!(f, "not signed by the sender"),
+            MempoolError::StaleNonce => write!(f, "nonce is already used"),
+            MempoolError::ReplacementUnderpriced => write!(
+                f,
+                "replacement must raise the fee by at least {}%",
+                REPLACEMENT_FEE_BUMP_PERCENT
+            ),
+            MempoolError::PoolFull => write!(f, "pool is full of better paying transactions"),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    tx: SignedTransaction,
+    arrival: u64, // breaks fee ties in favour of whoever came first
+}
+
+/// Transactions waiting for a block, kept per sender in nonce order.
+///
+/// Nonces may have gaps: a transaction only becomes selectable once every
+/// nonce before it is used or selected. At most one transaction is kept per
+/// sender and nonce; a second one replaces it only by paying enough more.
+#[derive(Clone, Debug)]
+pub struct Mempool {
+    queues: HashMap<Address, BTreeMap<u64, Entry>>,
+    len: usize,
+    capacity: usize,
+    arrivals: u64,
+}
+
+impl Mempool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queues: HashMap::new(),
+            len: 0,
+            capacity,
+            arrivals: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, sender: Address, nonce: u64) -> Option<&SignedTransaction> {
+        Some(&self.queues.get(&sender)?.get(&nonce)?.tx)
+    }
+
+    /// Adds `tx`, returning the transaction it replaced if any. Signatures
+    /// are the caller's to check.
+    ///
+    /// A full pool first drops whatever `state` made stale, then the end of
+    /// some sender's queue: one stuck behind a nonce gap if there is any,
+    /// otherwise the cheapest provided `tx` pays more. Only queue ends are
+    /// evicted so no sender is left with a gap, and never the one `tx`
+    /// would follow.
+    pub fn insert(
+        &mut self,
+        tx: SignedTransaction,
+        state: &State,
+    ) -> Result<Option<SignedTransaction>, MempoolError> {
+        let (sender, nonce) = (tx.tx.from, tx.tx.nonce);
+        if nonce < state.nonce(sender) {
+            return Err(MempoolError::StaleNonce);
+        }
+        if let Some(existing) = self.get(sender, nonce) {
+            if !pays_replacement_fee(existing.tx.fee, tx.tx.fee) {
+                return Err(MempoolError::ReplacementUnderpriced);
+            }
+            let entry = self.entry(tx);
+            let queue = self.queues.get_mut(&sender).expect("has an entry");
+            let replaced = queue.insert(nonce, entry).expect("has an entry");
+            return Ok(Some(replaced.tx));
+        }
+
+        if self.len >= self.capacity {
+            self.remove_stale(state);
+        }
+        if self.len >= self.capacity {
+            let evictable = self
+                .queues
+                .iter()
+                .filter_map(|(&queue_sender, queue)| {
+                    let (&last, entry) = queue.last_key_value()?;
+                    if queue_sender == sender && last < nonce {
+                        return None; // tx's own predecessor
+                    }
+                    // Stale entries are gone, so any missing nonce is a gap
+                    let count = queue.len() + usize::from(queue_sender == sender);
+                    let stuck = last - state.nonce(queue_sender) >= count as u64;
+                    let key = (!stuck, entry.tx.tx.fee, Reverse(entry.arrival));
+                    Some((key, queue_sender, last))
+                })
+                .min();
+            match evictable {
+                Some(((executable, fee, _), sender, nonce)) if !executable || fee < tx.tx.fee => {
+                    self.remove(sender, nonce);
+                }
+                _ => return Err(MempoolError::PoolFull),
+            }
+        }
+
+        let entry = self.entry(tx);
+        self.queues.entry(sender).or_default().insert(nonce, entry);
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Drops every transaction whose nonce `state` has already used.
+    pub fn remove_stale(&mut self, state: &State) {
+        let mut removed = 0;
+        self.queues.retain(|&sender, queue| {
+            let current = queue.split_off(&state.nonce(sender));
+            removed += queue.len();
+            *queue = current;
+            !queue.is_empty()
+        });
+        self.len -= removed;
+    }
+
+    /// Offers transactions to `include` from the highest fee down, each only
+    /// after all of its sender's earlier nonces, starting from the ones
+    /// `state` expects next. A transaction `include` turns down holds back
+    /// the rest of its sender's queue.
+    pub fn select(&self, state: &State, mut include: impl FnMut(&SignedTransaction) -> bool) {
+        let mut ready = BinaryHeap::new();
+        for (&sender, queue) in &self.queues {
+            if let Some(entry) = queue.get(&state.nonce(sender)) {
+                ready.push(Candidate::new(entry));
+            }
+        }
+        while let Some(Candidate { sender, nonce, .. }) = ready.pop() {
+            let queue = &self.queues[&sender];
+            if !include(&queue[&nonce].tx) {
+                continue;
+            }
+            let next = nonce.checked_add(1).and_then(|next| queue.get(&next));
+            if let Some(entry) = next {
+                ready.push(Candidate::new(entry));
+            }
+        }
+    }
+
+    fn entry(&mut self, tx: SignedTransaction) -> Entry {
+        self.arrivals += 1;
+        Entry {
+            tx,
+            arrival: self.arrivals,
+        }
+    }
+
+    fn remove(&mut self, sender: Address, nonce: u64) {
+        let queue = self.queues.get_mut(&sender).expect("sender has a queue");
+        queue.remove(&nonce);
+        if queue.is_empty() {
+            self.queues.remove(&sender);
+        }
+        self.len -= 1;
+    }
+}
+
+fn pays_replacement_fee(old: Balance, new: Balance) -> bool {
+    new > old && new as u128 * 100 >= old as u128 * (100 + REPLACEMENT_FEE_BUMP_PERCENT as u128)
+}
+
+// Ordered by fee, then earliest arrival
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Candidate {
+    fee: Balance,
+    arrival: Reverse<u64>,
+    sender: Address,
+    nonce: u64,
+}
+
+impl Candidate {
+    fn new(entry: &Entry) -> Self {
+        Self {
+            fee: entry.tx.tx.fee,
+            arrival: Reverse(entry.arrival),
+            sender: entry.tx.tx.from,
+            nonce: entry.tx.tx.nonce,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::state::{NATIVE_ASSET, Transaction, TxKind};
+
+    // The pool doesn't look at signatures, so these go without
+    fn tx(sender: Address, nonce: u64, fee: Balance) -> SignedTransaction {
+        SignedTransaction {
+            tx: Transaction {
+                kind: TxKind::Transfer,
+                from: sender,
+                to: 0,
+                asset: NATIVE_ASSET,
+                amount: 1,
+                fee,
+                nonce,
+            },
+            public_key: [0; 48],
+            signature: [0; 96],
+        }
+    }
+
+    fn selected(pool: &Mempool, state: &State) -> Vec<(Address, u64)> {
+        let mut selected = vec![];
+        pool.select(state, |tx| {
+            selected.push((tx.tx.from, tx.tx.nonce));
+            true
+        });
+        selected
+    }
+
+    fn fill(pool: &mut Mempool, state: &State, txs: &[(Address, u64, Balance)]) {
+        for &(sender, nonce, fee) in txs {
+            pool.insert(tx(sender, nonce, fee), state).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_selection_follows_fees_within_nonce_order() {
+        let state = State::new();
+        let mut pool = Mempool::new(16);
+        fill(
+            &mut pool,
+            &state,
+            &[(1, 0, 1), (1, 1, 10), (2, 0, 5), (3, 0, 5), (3, 1, 4)],
+        );
+
+        // Sender 1's high fee waits behind its cheap first nonce; 2 and 3 tie on
+        // fee, so 2 goes first for having arrived first
+        assert_eq!(
+            selected(&pool, &state),
+            [(2, 0), (3, 0), (3, 1), (1, 0), (1, 1)]
+        );
+
+        // A transaction left out holds back its sender's later nonces only
+        let mut offered = vec![];
+        pool.select(&state, |tx| {
+            offered.push((tx.tx.from, tx.tx.nonce));
+            tx.tx.from != 3
+        });
+        assert_eq!(offered, [(2, 0), (3, 0), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_gapped_nonces_wait_for_the_gap() {
+        let mut state = State::new();
+        state.nonces.insert(1, 3);
+        let mut pool = Mempool::new(16);
+        fill(
+            &mut pool,
+            &state,
+            &[(1, 3, 1), (1, 5, 9), (1, 6, 9), (2, 1, 9)],
+        );
+        assert_eq!(selected(&pool, &state), [(1, 3)]);
+
+        fill(&mut pool, &state, &[(1, 4, 1), (2, 0, 1)]);
+        assert_eq!(
+            selected(&pool, &state),
+            [(1, 3), (1, 4), (1, 5), (1, 6), (2, 0), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_replace_by_fee() {
+        let state = State::new();
+        let mut pool = Mempool::new(16);
+        fill(&mut pool, &state, &[(1, 0, 20)]);
+
+        assert_eq!(
+            pool.insert(tx(1, 0, 21), &state),
+            Err(MempoolError::ReplacementUnderpriced)
+        );
+        assert_eq!(pool.insert(tx(1, 0, 22), &state), Ok(Some(tx(1, 0, 20))));
+        assert_eq!(pool.get(1, 0), Some(&tx(1, 0, 22)));
+        assert_eq!(pool.len(), 1);
+
+        // Even a free transaction can only be replaced by one that pays something
+        fill(&mut pool, &state, &[(2, 0, 0)]);
+        assert_eq!(
+            pool.insert(tx(2, 0, 0), &state),
+            Err(MempoolError::ReplacementUnderpriced)
+        );
+        assert_eq!(pool.insert(tx(2, 0, 1), &state), Ok(Some(tx(2, 0, 0))));
+    }
+
+    #[test]
+    fn test_stale_transactions_are_rejected_and_pruned() {
+        let mut state = State::new();
+        let mut pool = Mempool::new(16);
+        fill(
+            &mut pool,
+            &state,
+            &[(1, 0, 1), (1, 1, 1), (1, 2, 1), (2, 0, 1)],
+        );
+
+        state.nonces.insert(1, 2);
+        assert_eq!(
+            pool.insert(tx(1, 1, 50), &state),
+            Err(MempoolError::StaleNonce)
+        );
+        pool.remove_stale(&state);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(selected(&pool, &state), [(1, 2), (2, 0)]);
+
+        state.nonces.insert(1, 3);
+        state.nonces.insert(2, 1);
+        pool.remove_stale(&state);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_full_pool_makes_room_from_stale_then_cheapest() {
+        let mut state = State::new();
+        let mut pool = Mempool::new(4);
+        fill(
+            &mut pool,
+            &state,
+            &[(1, 0, 1), (1, 1, 8), (2, 0, 3), (3, 0, 5)],
+        );
+
+        // Stale entries go first, however well they pay
+        state.nonces.insert(1, 1);
+        assert_eq!(pool.insert(tx(4, 0, 2), &state), Ok(None));
+        assert_eq!(pool.get(1, 0), None);
+
+        // Then the cheapest queue end, but only for a better paying newcomer
+        assert_eq!(
+            pool.insert(tx(5, 0, 2), &state),
+            Err(MempoolError::PoolFull)
+        );
+        assert_eq!(pool.insert(tx(5, 0, 3), &state), Ok(None));
+        assert_eq!(pool.get(4, 0), None);
+        assert_eq!(pool.len(), 4);
+
+        // Sender 2 and 5 tie on fee; the later arrival is evicted
+        assert_eq!(pool.insert(tx(6, 0, 4), &state), Ok(None));
+        assert_eq!(pool.get(5, 0), None);
+        assert!(pool.get(2, 0).is_some());
+    }
+
+    #[test]
+    fn test_eviction_keeps_the_newcomers_predecessor() {
+        let state = State::new();
+        let mut pool = Mempool::new(2);
+        fill(&mut pool, &state, &[(1, 0, 1), (2, 0, 5)]);
+
+        // Evicting (1, 0) would leave the newcomer behind a gap
+        assert_eq!(pool.insert(tx(1, 1, 9), &state), Ok(None));
+        assert!(pool.get(1, 0).is_some());
+        assert_eq!(pool.get(2, 0), None);
+
+        // With nothing else to evict, the newcomer is turned away
+        assert_eq!(
+            pool.insert(tx(1, 2, 20), &state),
+            Err(MempoolError::PoolFull)
+        );
+    }
+
+    #[test]
+    fn test_eviction_prefers_entries_stuck_behind_a_gap() {
+        let state = State::new();
+        let mut pool = Mempool::new(3);
+        fill(&mut pool, &state, &[(1, 0, 1), (2, 1, 9), (3, 0, 2)]);
+
+        // Sender 2 is missing nonce 0, so its better paying entry goes first
+        assert_eq!(pool.insert(tx(4, 0, 1), &state), Ok(None));
+        assert_eq!(pool.get(2, 1), None);
+
+        // A newcomer filling its own gap makes its queue executable again
+        let mut pool = Mempool::new(3);
+        fill(&mut pool, &state, &[(1, 0, 1), (2, 1, 9), (2, 2, 9)]);
+        assert_eq!(pool.insert(tx(2, 0, 3), &state), Ok(None));
+        assert_eq!(pool.get(1, 0), None);
+        assert_eq!(pool.len(), 3);
+    }
+
+    #[test]
+    fn test_many_senders() {
+        let mut state = State::new();
+        let mut pool = Mempool::new(1000);
+        for sender in 0..50 {
+            state.nonces.insert(sender, sender % 3);
+            // Every fifth sender is missing its next nonce
+            let first = state.nonce(sender) + u64::from(sender % 5 == 0);
+            for nonce in first..first + 4 {
+                pool.insert(tx(sender, nonce, (sender * 7 + nonce) % 11), &state)
+                    .unwrap();
+            }
+        }
+        assert_eq!(pool.len(), 200);
+
+        let selected = selected(&pool, &state);
+        assert_eq!(selected.len(), 160);
+        assert!(selected.iter().all(|&(sender, _)| sender % 5 != 0));
+        for sender in (0..50).filter(|sender| sender % 5 != 0) {
+            let nonces: Vec<_> = selected
+                .iter()
+                .filter(|&&(from, _)| from == sender)
+                .map(|&(_, nonce)| nonce)
+                .collect();
+            let next = state.nonce(sender);
+            assert_eq!(nonces, (next..next + 4).collect::<Vec<_>>());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_selection_never_breaks_nonce_order(
+            nonces in prop::collection::vec(0..3u64, 8),
+            txs in prop::collection::vec((0..8u64, 0..8u64, 0..20u64), 1..60),
+            capacity in 1..40usize,
+        ) {
+            let mut state = State::new();
+            state.nonces.extend(nonces.iter().copied().enumerate().map(|(s, n)| (s as u64, n)));
+            let mut pool = Mempool::new(capacity);
+            for (sender, nonce, fee) in txs {
+                let _ = pool.insert(tx(sender, nonce, fee), &state);
+                prop_assert!(pool.len() <= capacity);
+            }
+
+            let mut next: HashMap<Address, u64> = HashMap::new();
+            for (sender, nonce) in selected(&pool, &state) {
+                let expected = next.entry(sender).or_insert(state.nonce(sender));
+                prop_assert_eq!(nonce, *expected);
+                *expected += 1;
+            }
+            // Whatever wasn't selected is stuck behind a gap
+            for (&sender, queue) in &pool.queues {
+                let expected = next.get(&sender).copied().unwrap_or(state.nonce(sender));
+                prop_assert!(!queue.contains_key(&expected));
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::hasher::{Hasher, RollupHasher};
+
+pub type Hash = [u8; 32];
+
+pub const EMPTY_ROOT: Hash = [0u8; 32];
+
+// Domain separation so a leaf can never be passed off as an inner node
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    hash_leaf_with::<RollupHasher>(data)
+}
+
+pub fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    hash_node_with::<RollupHasher>(left, right)
+}
+
+/// [`hash_leaf`] under a hasher other than the configured one.
+pub fn hash_leaf_with<H: Hasher>(data: &[u8]) -> Hash {
+    H::digest(&[&[LEAF_PREFIX], data])
+}
+
+/// [`hash_node`] under a hasher other than the configured one.
+pub fn hash_node_with<H: Hasher>(left: &Hash, right: &Hash) -> Hash {
+    H::digest(&[&[NODE_PREFIX], left, right])
+}
+
+/// Root of a binary Merkle tree over already-hashed leaves.
+///
+/// An odd node at the end of a level is carried up unchanged rather than
+/// paired with itself, so `[a, b, c]` and `[a, b, c, c]` commit differently.
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    merkle_root_with::<RollupHasher>(leaves)
+}
+
+/// [`merkle_root`] under a hasher other than the configured one.
+pub fn merkle_root_with<H: Hasher>(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return EMPTY_ROOT;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level::<H>(&level);
+    }
+    level[0]
+}
+
+fn next_level<H: Hasher>(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node_with::<H>(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<Hash>, // bottom-up, levels where the node was carried up have no entry
+}
+
+impl MerkleProof {
+    /// Whether `leaf` is at `index` of a `leaf_count` wide tree under `root`.
+    /// The root doesn't commit to the width, so callers must check
+    /// `leaf_count` against a trusted one.
+    pub fn verify(&self, root: &Hash, leaf: &Hash) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut node = *leaf;
+        let mut index = self.index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            if index ^ 1 < width {
+                let Some(sibling) = siblings.next() else {
+                    return false;
+                };
+                node = if index.is_multiple_of(2) {
+                    hash_node(&node, sibling)
+                } else {
+                    hash_node(sibling, &node)
+                };
+            }
+            index /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none() && node == *root
+    }
+}
+
+pub fn merkle_proof(leaves: &[Hash], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut siblings = vec![];
+    let mut level = leaves.to_vec();
+    let mut i = index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(i ^ 1) {
+            siblings.push(*sibling);
+        }
+        level = next_level::<RollupHasher>(&level);
+        i /= 2;
+    }
+    Some(MerkleProof {
+        index,
+        leaf_count: leaves.len(),
+        siblings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u8) -> Vec<Hash> {
+        (0..n).map(|i| hash_leaf(&[i])).collect()
+    }
+
+    #[test]
+    fn test_proofs_verify_for_every_leaf() {
+        for n in 1..=9 {
+            let leaves = leaves(n);
+            let root = merkle_root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, i).unwrap();
+                assert!(proof.verify(&root, leaf), "leaf {i} of {n}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_rejects_wrong_leaf_or_index() {
+        let leaves = leaves(5);
+        let root = merkle_root(&leaves);
+        let proof = merkle_proof(&leaves, 2).unwrap();
+
+        assert!(!proof.verify(&root, &leaves[3]));
+        let moved = MerkleProof {
+            index: 3,
+            ..proof.clone()
+        };
+        assert!(!moved.verify(&root, &leaves[2]));
+        assert!(merkle_proof(&leaves, 5).is_none());
+    }
+
+    #[test]
+    fn test_odd_leaf_is_not_duplicated() {
+        let mut leaves = leaves(3);
+        let root = merkle_root(&leaves);
+        leaves.push(leaves[2]);
+        assert_ne!(root, merkle_root(&leaves));
+    }
+}
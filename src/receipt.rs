@@ -0,0 +1,79 @@
+use crate::merkle::{Hash, MerkleProof, hash_leaf, merkle_proof, merkle_root};
+use crate::state::TxError;
+
+/// Outcome of one transaction in a block. A failed transaction changes
+/// nothing, not even its sender's nonce, and its receipt says why.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Receipt {
+    Success,
+    Failed(TxError),
+}
+
+impl Receipt {
+    /// Canonical one-byte form: 0 for success, 1 + the error's position in
+    /// `TxError` otherwise.
+    pub fn encode(&self) -> u8 {
+        match self {
+            Receipt::Success => 0,
+            Receipt::Failed(error) => 1 + *error as u8,
+        }
+    }
+
+    pub fn leaf(&self) -> Hash {
+        hash_leaf(&[self.encode()])
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == Receipt::Success
+    }
+}
+
+impl From<Result<(), TxError>> for Receipt {
+    fn from(result: Result<(), TxError>) -> Self {
+        match result {
+            Ok(()) => Receipt::Success,
+            Err(error) => Receipt::Failed(error),
+        }
+    }
+}
+
+/// Merkle root over a block's receipts, in transaction order.
+pub fn receipts_root(receipts: &[Receipt]) -> Hash {
+    let leaves: Vec<Hash> = receipts.iter().map(Receipt::leaf).collect();
+    merkle_root(&leaves)
+}
+
+/// Proof that `receipts[index]` is under `receipts_root(receipts)`.
+pub fn receipt_proof(receipts: &[Receipt], index: usize) -> Option<MerkleProof> {
+    let leaves: Vec<Hash> = receipts.iter().map(Receipt::leaf).collect();
+    merkle_proof(&leaves, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipts_commit_to_outcome_and_position() {
+        let receipts = [
+            Receipt::Success,
+            Receipt::Failed(TxError::InsufficientBalance),
+            Receipt::Failed(TxError::BadNonce),
+        ];
+        let root = receipts_root(&receipts);
+
+        for (i, receipt) in receipts.iter().enumerate() {
+            let proof = receipt_proof(&receipts, i).unwrap();
+            assert!(proof.verify(&root, &receipt.leaf()));
+        }
+
+        // Neither the reason nor the order can be swapped after the fact
+        let proof = receipt_proof(&receipts, 1).unwrap();
+        assert!(!proof.verify(&root, &Receipt::Failed(TxError::Overflow).leaf()));
+        assert!(!proof.verify(&root, &receipts[2].leaf()));
+        assert_ne!(
+            root,
+            receipts_root(&[receipts[0], receipts[2], receipts[1]])
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use crate::codec::Codec;
+use crate::mempool::{Mempool, MempoolError};
+use crate::receipt::Receipt;
+use crate::signature::{SignedTransaction, aggregate_signatures};
+use crate::state::{BlockContext, State};
+use crate::verifier::{BlockStatus, L1Verifier, RollupBlock, VerifierError};
+use crate::{Address, BlockNumber};
+
+/// Cap on the encoded size of a block's transactions unless configured otherwise.
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 128 * 1024;
+
+/// Transactions the mempool holds unless configured otherwise.
+pub const DEFAULT_MEMPOOL_CAPACITY: usize = 4096;
+
+/// An honest block producer. It keeps the L2 state at the tip of the chain
+/// it submitted and queues transactions until they fit into a block.
+pub struct Sequencer {
+    address: Address,  // L1 account its block bonds are locked from
+    coinbase: Address, // L2 account its blocks credit tips to
+    state: State,
+    mempool: Mempool,
+    history: Vec<SubmittedBlock>, // accepted blocks that aren't final yet, oldest first
+    max_txs: usize,
+    max_block_bytes: usize,
+}
+
+/// What it takes to undo a submitted block should L1 revert it.
+struct SubmittedBlock {
+    block_number: BlockNumber,
+    pre_state: State,
+    transactions: Vec<SignedTransaction>,
+}
+
+impl Sequencer {
+    pub fn new(address: Address, coinbase: Address, genesis: State) -> Self {
+        Self {
+            address,
+            coinbase,
+            state: genesis,
+            mempool: Mempool::new(DEFAULT_MEMPOOL_CAPACITY),
+            history: vec![],
+            max_txs: usize::MAX,
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+        }
+    }
+
+    /// Caps each block at `max_txs` transactions, on top of what the fee
+    /// market allows, and `max_block_bytes` of encoded transactions.
+    pub fn with_limits(mut self, max_txs: usize, max_block_bytes: usize) -> Self {
+        self.max_txs = max_txs;
+        self.max_block_bytes = max_block_bytes;
+        self
+    }
+
+    /// L2 state after the last block this sequencer got accepted.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Holds at most `capacity` queued transactions instead of the default.
+    pub fn with_mempool_capacity(mut self, capacity: usize) -> Self {
+        self.mempool = Mempool::new(capacity);
+        self
+    }
+
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+
+    /// Queues `tx` if it is signed by its sender and its nonce isn't used
+    /// yet, returning the transaction it replaced if any. Anything else, such
+    /// as a nonce gap or a balance that doesn't cover it, may still be
+    /// resolved by the time a block is built.
+    pub fn submit_transaction(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<Option<SignedTransaction>, MempoolError> {
+        if !tx.verify_signature() {
+            return Err(MempoolError::BadSignature);
+        }
+        self.mempool.insert(tx, &self.state)
+    }
+
+    /// Block extending `l1`'s tip with as many queued transactions as apply
+    /// cleanly and fit the limits, highest fees first. Assumes the sequencer
+    /// is in sync with `l1`.
+    pub fn build_block(&self, l1: &L1Verifier) -> RollupBlock {
+        self.build(l1).0
+    }
+
+    /// Builds the next block and submits it, bonded from the sequencer's L1
+    /// account, after catching up with any reverts. Once accepted, its
+    /// transactions leave the mempool and its post-state becomes the
+    /// sequencer's.
+    pub fn submit_block(&mut self, l1: &mut L1Verifier) -> Result<BlockNumber, VerifierError> {
+        self.sync(l1);
+        let pre_state = self.state.clone();
+        let (block, post_state) = self.build(l1);
+        let block_number = block.block_number;
+        let transactions = block.transactions.clone();
+        l1.submit_block(self.address, block)?;
+
+        self.history.push(SubmittedBlock {
+            block_number,
+            pre_state,
+            transactions,
+        });
+        self.state = post_state;
+        self.mempool.remove_stale(&self.state);
+        Ok(block_number)
+    }
+
+    /// Rolls back to `l1`'s tip: the state goes back to before the first
+    /// reverted block and the reverted transactions are queued again, as far
+    /// as the mempool has room. Blocks that became final are forgotten.
+    pub fn sync(&mut self, l1: &L1Verifier) {
+        let mut reverted = vec![];
+        while let Some(block) = self
+            .history
+            .pop_if(|b| b.block_number >= l1.next_block_number())
+        {
+            self.state = block.pre_state;
+            reverted.push(block.transactions);
+        }
+        for tx in reverted.into_iter().rev().flatten() {
+            let _ = self.mempool.insert(tx, &self.state);
+        }
+
+        if let Some(finalized) = l1.latest_finalized_block() {
+            self.history
+                .retain(|block| block.block_number > finalized.block_number);
+        }
+    }
+
+    fn build(&self, l1: &L1Verifier) -> (RollupBlock, State) {
+        let context = BlockContext {
+            coinbase: self.coinbase,
+            base_fee: l1.next_base_fee(),
+        };
+        let max_txs = self.max_txs.min(l1.max_txs());
+        let mut state = self.state.clone();
+        let mut transactions = vec![];
+        let mut state_roots = vec![];
+        let mut bytes = 0;
+
+        self.mempool.select(&self.state, |tx| {
+            let size = encoded_len(tx);
+            if transactions.len() == max_txs || bytes + size > self.max_block_bytes {
+                return false;
+            }
+            if state.apply_signed_tx(tx, &context).is_err() {
+                return false;
+            }
+            bytes += size;
+            transactions.push(tx.clone());
+            state_roots.push(state.root());
+            true
+        });
+
+        let block = RollupBlock {
+            block_number: l1.next_block_number(),
+            parent_hash: l1.tip_hash(),
+            sequencer: self.address,
+            coinbase: self.coinbase,
+            base_fee: context.base_fee,
+            timestamp: l1.time(),
+            pre_state_root: self.state.root(),
+            aggregate_signature: aggregate_signatures(&transactions)
+                .expect("signatures are checked on entry"),
+            receipts: vec![Receipt::Success; transactions.len()],
+            transactions,
+            state_roots,
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        };
+        (block, state)
+    }
+}
+
+fn encoded_len(tx: &SignedTransaction) -> usize {
+    let mut bytes = vec![];
+    tx.write(&mut bytes);
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Balance;
+    use crate::fee_market::FeeMarket;
+    use crate::state::{NATIVE_ASSET, Transaction, TxKind};
+    use crate::test_utils::{COINBASE, addr, key, setup_state, transfer};
+
+    const SEQUENCER: Address = 10;
+
+    fn transfer_with_fee(
+        from: u8,
+        to: u8,
+        amount: Balance,
+        nonce: u64,
+        fee: Balance,
+    ) -> SignedTransaction {
+        key(from).sign(Transaction {
+            kind: TxKind::Transfer,
+            from: addr(from),
+            to: addr(to),
+            asset: NATIVE_ASSET,
+            amount,
+            fee,
+            nonce,
+        })
+    }
+
+    fn setup() -> (Sequencer, L1Verifier) {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root())
+            .with_bonds(10, 10)
+            .with_fee_market(FeeMarket {
+                initial_base_fee: 0,
+                target_txs: 2,
+            });
+        l1.deposit(SEQUENCER, 100).unwrap();
+        (Sequencer::new(SEQUENCER, COINBASE, genesis), l1)
+    }
+
+    #[test]
+    fn test_submitted_blocks_extend_l1_and_finalize() {
+        let (mut sequencer, mut l1) = setup();
+        sequencer.submit_transaction(transfer(1, 2, 40, 0)).unwrap();
+        sequencer.submit_transaction(transfer(2, 1, 80, 0)).unwrap(); // needs the 40 first
+        assert_eq!(sequencer.submit_block(&mut l1), Ok(0));
+        assert!(sequencer.mempool().is_empty());
+        assert_eq!(sequencer.state().balance(addr(1), NATIVE_ASSET), 140);
+        assert_eq!(sequencer.state().balance(addr(2), NATIVE_ASSET), 10);
+        assert_eq!(l1.tip_state_root(), sequencer.state().root());
+
+        // An empty block still moves the chain forward, at the fee it now calls for
+        l1.advance_time(1);
+        assert_eq!(sequencer.submit_block(&mut l1), Ok(1));
+        let block = sequencer.build_block(&l1);
+        assert_eq!(block.block_number, 2);
+        assert_eq!(block.parent_hash, l1.tip_hash());
+        assert_eq!(block.base_fee, l1.next_base_fee());
+
+        l1.advance_time(5);
+        assert_eq!(l1.latest_finalized_block().unwrap().block_number, 1);
+        assert_eq!(l1.ledger().balance(SEQUENCER), 100);
+    }
+
+    #[test]
+    fn test_reverted_transactions_are_resubmitted() {
+        let (mut sequencer, mut l1) = setup();
+        l1.deposit(99, 10).unwrap();
+        sequencer.submit_transaction(transfer(1, 2, 40, 0)).unwrap();
+        assert_eq!(sequencer.submit_block(&mut l1), Ok(0));
+        l1.advance_time(1);
+        sequencer.submit_transaction(transfer(1, 2, 10, 1)).unwrap();
+        assert_eq!(sequencer.submit_block(&mut l1), Ok(1));
+
+        // Nobody defends #0 against a dispute, so it and #1 are reverted
+        l1.open_dispute(0, 99).unwrap();
+        l1.advance_time(6);
+        assert_eq!(l1.next_block_number(), 0);
+        assert_ne!(l1.tip_state_root(), sequencer.state().root());
+
+        assert_eq!(sequencer.submit_block(&mut l1), Ok(0));
+        assert_eq!(sequencer.history.len(), 1);
+        assert!(sequencer.mempool().is_empty());
+        assert_eq!(sequencer.state().balance(addr(1), NATIVE_ASSET), 50);
+        assert_eq!(sequencer.state().balance(addr(2), NATIVE_ASSET), 100);
+        assert_eq!(l1.tip_state_root(), sequencer.state().root());
+
+        // Final blocks can't be reverted, so they're no longer kept
+        l1.advance_time(6);
+        sequencer.sync(&l1);
+        assert!(sequencer.history.is_empty());
+    }
+
+    #[test]
+    fn test_transactions_wait_until_they_apply() {
+        let (mut sequencer, mut l1) = setup();
+        let mut forged = transfer(1, 2, 10, 0);
+        forged.tx.amount = 90;
+        assert_eq!(
+            sequencer.submit_transaction(forged),
+            Err(MempoolError::BadSignature)
+        );
+
+        // Nonce 2 waits on nonce 1, which can't be afforded yet
+        sequencer.submit_transaction(transfer(1, 2, 10, 2)).unwrap();
+        sequencer
+            .submit_transaction(transfer(1, 2, 500, 1))
+            .unwrap();
+        sequencer.submit_transaction(transfer(1, 2, 10, 0)).unwrap();
+        sequencer.submit_block(&mut l1).unwrap();
+        assert_eq!(sequencer.state().nonce(addr(1)), 1);
+        assert_eq!(sequencer.mempool().len(), 2);
+        assert_eq!(
+            sequencer.submit_transaction(transfer(1, 2, 10, 0)),
+            Err(MempoolError::StaleNonce)
+        );
+
+        // A better paying replacement for nonce 1 unblocks nonce 2
+        let overdraft = sequencer.mempool().get(addr(1), 1).cloned();
+        assert_eq!(
+            sequencer.submit_transaction(transfer(1, 2, 5, 1)),
+            Err(MempoolError::ReplacementUnderpriced)
+        );
+        assert_eq!(
+            sequencer.submit_transaction(transfer_with_fee(1, 2, 5, 1, 2)),
+            Ok(overdraft)
+        );
+        sequencer.submit_block(&mut l1).unwrap();
+        assert_eq!(sequencer.state().nonce(addr(1)), 3);
+        assert!(sequencer.mempool().is_empty());
+    }
+
+    #[test]
+    fn test_full_blocks_go_to_the_highest_fees() {
+        let (sequencer, mut l1) = setup();
+        let mut sequencer = sequencer.with_limits(2, DEFAULT_MAX_BLOCK_BYTES);
+        sequencer.submit_transaction(transfer(1, 2, 10, 0)).unwrap();
+        sequencer.submit_transaction(transfer(1, 2, 10, 1)).unwrap();
+        sequencer
+            .submit_transaction(transfer_with_fee(2, 1, 10, 0, 3))
+            .unwrap();
+
+        sequencer.submit_block(&mut l1).unwrap();
+        assert_eq!(sequencer.state().nonce(addr(2)), 1);
+        assert_eq!(sequencer.state().nonce(addr(1)), 1);
+        assert_eq!(sequencer.state().balance(COINBASE, NATIVE_ASSET), 3);
+        assert!(sequencer.mempool().get(addr(1), 1).is_some());
+    }
+
+    #[test]
+    fn test_blocks_respect_size_limits() {
+        let (sequencer, l1) = setup();
+        let txs: Vec<_> = (0..6).map(|nonce| transfer(1, 2, 1, nonce)).collect();
+        let tx_len = encoded_len(&txs[0]);
+
+        // The fee market caps blocks at twice its target of 2
+        let mut sequencer = sequencer.with_limits(usize::MAX, DEFAULT_MAX_BLOCK_BYTES);
+        for tx in &txs {
+            sequencer.submit_transaction(tx.clone()).unwrap();
+        }
+        assert_eq!(sequencer.build_block(&l1).transactions, txs[..4]);
+
+        let sequencer = sequencer.with_limits(3, DEFAULT_MAX_BLOCK_BYTES);
+        assert_eq!(sequencer.build_block(&l1).transactions.len(), 3);
+
+        let sequencer = sequencer.with_limits(3, 2 * tx_len + 1);
+        assert_eq!(sequencer.build_block(&l1).transactions, txs[..2]);
+    }
+}
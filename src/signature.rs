@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+
+use blst::BLST_ERROR;
+use blst::min_pk::{AggregateSignature, PublicKey, SecretKey, Signature};
+use sha2::{Digest, Sha256};
+
+use crate::Address;
+use crate::state::Transaction;
+
+/// Compressed BLS12-381 G1 public key.
+pub type PublicKeyBytes = [u8; 48];
+/// Compressed BLS12-381 G2 signature.
+pub type SignatureBytes = [u8; 96];
+
+/// Ciphersuite tag for signing transactions, so they can't be replayed as
+/// signatures over anything else.
+const DST: &[u8] = b"ROLLUP_TX_BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// Aggregate of no signatures: the compressed point at infinity.
+pub const EMPTY_AGGREGATE: SignatureBytes = {
+    let mut bytes = [0u8; 96];
+    bytes[0] = 0xc0;
+    bytes
+};
+
+/// Address owned by `public_key`: the first 8 bytes of its SHA-256 hash.
+pub fn address_of(public_key: &PublicKeyBytes) -> Address {
+    let digest = Sha256::digest(public_key);
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    Address::from_be_bytes(prefix)
+}
+
+pub struct Keypair {
+    secret: SecretKey,
+    public: PublicKey,
+}
+
+impl Keypair {
+    /// Derives a key deterministically from `seed`.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let secret = SecretKey::key_gen(seed, &[]).expect("seed has the 32 bytes key_gen needs");
+        let public = secret.sk_to_pk();
+        Self { secret, public }
+    }
+
+    pub fn public_key(&self) -> PublicKeyBytes {
+        self.public.compress()
+    }
+
+    pub fn address(&self) -> Address {
+        address_of(&self.public_key())
+    }
+
+    /// Signs `tx` as is; it only verifies if `tx.from` is this key's address.
+    pub fn sign(&self, tx: Transaction) -> SignedTransaction {
+        let signature = self.secret.sign(&tx.encode(), DST, &[]);
+        SignedTransaction {
+            tx,
+            public_key: self.public_key(),
+            signature: signature.compress(),
+        }
+    }
+}
+
+/// A transaction with its sender's public key and signature over
+/// `Transaction::encode`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub tx: Transaction,
+    pub public_key: PublicKeyBytes,
+    pub signature: SignatureBytes,
+}
+
+impl SignedTransaction {
+    /// Whether the key owns `tx.from` and signed exactly this transaction.
+    /// Malformed keys and signatures simply fail.
+    pub fn verify_signature(&self) -> bool {
+        if address_of(&self.public_key) != self.tx.from {
+            return false;
+        }
+        let Ok(public_key) = PublicKey::key_validate(&self.public_key) else {
+            return false;
+        };
+        let Ok(signature) = Signature::sig_validate(&self.signature, true) else {
+            return false;
+        };
+        signature.verify(false, &self.tx.encode(), DST, &[], &public_key, false)
+            == BLST_ERROR::BLST_SUCCESS
+    }
+}
+
+/// Combines the signatures of `txs` into the single one a block carries, or
+/// `None` if any of them isn't a valid signature encoding.
+pub fn aggregate_signatures(txs: &[SignedTransaction]) -> Option<SignatureBytes> {
+    if txs.is_empty() {
+        return Some(EMPTY_AGGREGATE);
+    }
+    let signatures = txs
+        .iter()
+        .map(|signed| Signature::sig_validate(&signed.signature, true))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let signatures: Vec<&Signature> = signatures.iter().collect();
+    let aggregate = AggregateSignature::aggregate(&signatures, false).ok()?;
+    Some(aggregate.to_signature().compress())
+}
+
+/// Checks in one pass that `aggregate` combines a valid signature by every
+/// sender in `txs`.
+///
+/// Plain BLS aggregation is only sound over distinct messages, so a batch
+/// carrying the same transaction twice never verifies this way.
+pub fn verify_aggregate(txs: &[SignedTransaction], aggregate: &SignatureBytes) -> bool {
+    if txs.is_empty() {
+        return *aggregate == EMPTY_AGGREGATE;
+    }
+    if txs
+        .iter()
+        .any(|signed| address_of(&signed.public_key) != signed.tx.from)
+    {
+        return false;
+    }
+    let messages: Vec<_> = txs.iter().map(|signed| signed.tx.encode()).collect();
+    if messages.iter().collect::<HashSet<_>>().len() != messages.len() {
+        return false;
+    }
+    let Ok(public_keys) = txs
+        .iter()
+        .map(|signed| PublicKey::key_validate(&signed.public_key))
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        return false;
+    };
+    let Ok(signature) = Signature::sig_validate(aggregate, true) else {
+        return false;
+    };
+    let messages: Vec<&[u8]> = messages.iter().map(|message| message.as_slice()).collect();
+    let public_keys: Vec<&PublicKey> = public_keys.iter().collect();
+    signature.aggregate_verify(false, &messages, DST, &public_keys, false)
+        == BLST_ERROR::BLST_SUCCESS
+}
+
+/// Indices of the transactions in `txs` that aren't signed by their sender.
+/// Only when `aggregate` doesn't verify is each signature checked on its own.
+pub fn invalid_signatures(txs: &[SignedTransaction], aggregate: &SignatureBytes) -> Vec<usize> {
+    if verify_aggregate(txs, aggregate) {
+        return vec![];
+    }
+    txs.iter()
+        .enumerate()
+        .filter(|(_, signed)| !signed.verify_signature())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{NATIVE_ASSET, TxKind};
+
+    #[test]
+    fn test_signature_verifies_only_for_signed_transaction() {
+        let alice = Keypair::from_seed(&[1; 32]);
+        let tx = Transaction {
+            kind: TxKind::Transfer,
+            from: alice.address(),
+            to: 2,
+            asset: NATIVE_ASSET,
+            amount: 40,
+            fee: 0,
+            nonce: 0,
+        };
+        let signed = alice.sign(tx.clone());
+        assert!(signed.verify_signature());
+
+        let mut tampered = signed.clone();
+        tampered.tx.amount = 400;
+        assert!(!tampered.verify_signature());
+
+        let mut malformed = signed.clone();
+        malformed.signature = [0xff; 96];
+        assert!(!malformed.verify_signature());
+    }
+
+    #[test]
+    fn test_cannot_sign_for_someone_elses_address() {
+        let alice = Keypair::from_seed(&[1; 32]);
+        let mallory = Keypair::from_seed(&[2; 32]);
+        assert_ne!(alice.address(), mallory.address());
+
+        // Valid signature, but by a key that doesn't own the sender address
+        let forged = mallory.sign(Transaction {
+            kind: TxKind::Transfer,
+            from: alice.address(),
+            to: mallory.address(),
+            asset: NATIVE_ASSET,
+            amount: 100,
+            fee: 0,
+            nonce: 0,
+        });
+        assert!(!forged.verify_signature());
+
+        // Nor can mallory claim alice's key with its own signature
+        let mut stolen_key = forged.clone();
+        stolen_key.public_key = alice.public_key();
+        assert!(!stolen_key.verify_signature());
+    }
+
+    fn batch(keys: &[Keypair]) -> Vec<SignedTransaction> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                key.sign(Transaction {
+                    kind: TxKind::Transfer,
+                    from: key.address(),
+                    to: 2,
+                    asset: NATIVE_ASSET,
+                    amount: 10,
+                    fee: 0,
+                    nonce: i as u64,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_covers_whole_batch() {
+        let keys: Vec<_> = (1..=4).map(|n| Keypair::from_seed(&[n; 32])).collect();
+        let txs = batch(&keys);
+        let aggregate = aggregate_signatures(&txs).unwrap();
+
+        assert!(verify_aggregate(&txs, &aggregate));
+        assert!(invalid_signatures(&txs, &aggregate).is_empty());
+        assert!(!verify_aggregate(&txs[..3], &aggregate)); // can't drop a tx
+        assert!(verify_aggregate(&[], &aggregate_signatures(&[]).unwrap()));
+        assert!(!verify_aggregate(&[], &aggregate));
+    }
+
+    #[test]
+    fn test_failed_aggregate_falls_back_to_each_signature() {
+        let keys: Vec<_> = (1..=4).map(|n| Keypair::from_seed(&[n; 32])).collect();
+        let mut txs = batch(&keys);
+        let aggregate = aggregate_signatures(&txs).unwrap();
+
+        // Bad aggregate over good signatures: nothing to blame on the transactions
+        assert!(!verify_aggregate(&txs, &EMPTY_AGGREGATE));
+        assert!(invalid_signatures(&txs, &EMPTY_AGGREGATE).is_empty());
+
+        // A tampered transaction breaks the aggregate and is pinpointed
+        txs[2].tx.amount = 1000;
+        assert!(!verify_aggregate(&txs, &aggregate));
+        assert_eq!(invalid_signatures(&txs, &aggregate), [2]);
+    }
+
+    #[test]
+    fn test_duplicate_transactions_are_checked_one_by_one() {
+        let key = Keypair::from_seed(&[1; 32]);
+        let mut txs = batch(std::slice::from_ref(&key));
+        txs.push(txs[0].clone());
+        let aggregate = aggregate_signatures(&txs).unwrap();
+
+        assert!(!verify_aggregate(&txs, &aggregate));
+        assert!(invalid_signatures(&txs, &aggregate).is_empty());
+    }
+}
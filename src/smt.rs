@@ -0,0 +1,393 @@
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Mutex, OnceLock};
+
+use crate::hasher::{Hasher, RollupHasher};
+use crate::merkle::{Hash, hash_node, hash_node_with};
+
+/// One level per bit of a `u64` key.
+pub const DEPTH: usize = 64;
+
+/// Value of a leaf slot that holds nothing.
+pub const EMPTY_LEAF: Hash = [0u8; 32];
+
+/// Hash of an all-empty subtree of each height under `H`, `[0]` being a
+/// single empty leaf.
+fn default_hashes<H: Hasher + 'static>() -> &'static [Hash; DEPTH + 1] {
+    // Statics in generic functions are shared by every `H`, hence the map
+    type Defaults = HashMap<TypeId, &'static [Hash; DEPTH + 1]>;
+    static DEFAULTS: OnceLock<Mutex<Defaults>> = OnceLock::new();
+    let mut cache = DEFAULTS.get_or_init(Mutex::default).lock().unwrap();
+    cache.entry(TypeId::of::<H>()).or_insert_with(|| {
+        let mut defaults = [EMPTY_LEAF; DEPTH + 1];
+        for height in 0..DEPTH {
+            defaults[height + 1] = hash_node_with::<H>(&defaults[height], &defaults[height]);
+        }
+        Box::leak(Box::new(defaults))
+    })
+}
+
+pub fn empty_root() -> Hash {
+    default_hashes::<RollupHasher>()[DEPTH]
+}
+
+/// Prefix identifying the node at `height` on the path to `key`.
+fn prefix(key: u64, height: usize) -> u64 {
+    key.checked_shr(height as u32).unwrap_or(0)
+}
+
+/// Whether the path to `key` goes right when leaving a node at `height`.
+fn goes_right(key: u64, height: usize) -> bool {
+    (key >> height) & 1 == 1
+}
+
+/// Sparse Merkle tree of depth 64 over `u64` keys.
+///
+/// Only non-empty nodes are stored; everything else is implied by the
+/// default hash of its height. Nodes are hashed with `H`, the configured
+/// [`RollupHasher`] unless built through [`SparseMerkleTree::with_hasher`].
+pub struct SparseMerkleTree<H: Hasher + 'static = RollupHasher> {
+    nodes: HashMap<(usize, u64), Hash>, // (height, prefix) -> hash, height 0 being leaves
+    defaults: &'static [Hash; DEPTH + 1],
+    hasher: PhantomData<H>,
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<H: Hasher + 'static> Default for SparseMerkleTree<H> {
+    fn default() -> Self {
+        Self::with_hasher()
+    }
+}
+
+impl<H: Hasher + 'static> Clone for SparseMerkleTree<H> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            ..Self::with_hasher()
+        }
+    }
+}
+
+impl<H: Hasher + 'static> fmt::Debug for SparseMerkleTree<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseMerkleTree")
+            .field("nodes", &self.nodes)
+            .finish()
+    }
+}
+
+impl<H: Hasher + 'static> SparseMerkleTree<H> {
+    /// An empty tree hashed with `H`.
+    pub fn with_hasher() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            defaults: default_hashes::<H>(),
+            hasher: PhantomData,
+        }
+    }
+
+    /// Rebuilds just the paths of the witnessed keys under `root`, enough to
+    /// read and update those keys without the rest of the tree.
+    ///
+    /// Returns `None` if any witness doesn't verify against `root`. Keys that
+    /// weren't witnessed must not be touched on the result.
+    pub fn from_witnesses<'a>(
+        root: &Hash,
+        witnesses: impl IntoIterator<Item = (&'a SmtProof, Option<Hash>)>,
+    ) -> Option<Self> {
+        let mut tree = Self::with_hasher();
+        for (proof, leaf) in witnesses {
+            let siblings = proof.expanded_siblings(tree.defaults)?;
+            let mut node = leaf.unwrap_or(EMPTY_LEAF);
+            tree.set_node(0, proof.key, node);
+            for (height, sibling) in siblings.iter().enumerate() {
+                tree.set_node(height, prefix(proof.key, height) ^ 1, *sibling);
+                node = if goes_right(proof.key, height) {
+                    hash_node_with::<H>(sibling, &node)
+                } else {
+                    hash_node_with::<H>(&node, sibling)
+                };
+                tree.set_node(height + 1, prefix(proof.key, height + 1), node);
+            }
+            if node != *root {
+                return None;
+            }
+        }
+        Some(tree)
+    }
+
+    pub fn root(&self) -> Hash {
+        self.node(DEPTH, 0)
+    }
+
+    pub fn get(&self, key: u64) -> Option<Hash> {
+        self.nodes.get(&(0, key)).copied()
+    }
+
+    /// Sets the leaf at `key`, or clears it when `leaf` is `None`.
+    pub fn update(&mut self, key: u64, leaf: Option<Hash>) {
+        self.set_node(0, key, leaf.unwrap_or(EMPTY_LEAF));
+        for height in 0..DEPTH {
+            let parent = self.parent_hash(height, prefix(key, height));
+            self.set_node(height + 1, prefix(key, height + 1), parent);
+        }
+    }
+
+    /// Applies several updates, hashing each shared ancestor only once.
+    pub fn update_batch(&mut self, updates: impl IntoIterator<Item = (u64, Option<Hash>)>) {
+        let mut dirty = BTreeSet::new();
+        for (key, leaf) in updates {
+            self.set_node(0, key, leaf.unwrap_or(EMPTY_LEAF));
+            dirty.insert(key);
+        }
+        for height in 0..DEPTH {
+            let parents: BTreeSet<u64> = dirty.iter().map(|p| p >> 1).collect();
+            for &parent in &parents {
+                let hash = self.parent_hash(height, parent << 1);
+                self.set_node(height + 1, parent, hash);
+            }
+            dirty = parents;
+        }
+    }
+
+    pub fn prove(&self, key: u64) -> SmtProof {
+        let mut bitmap = 0u64;
+        let mut siblings = vec![];
+        for height in 0..DEPTH {
+            let sibling = self.node(height, prefix(key, height) ^ 1);
+            if sibling != self.defaults[height] {
+                bitmap |= 1 << height;
+                siblings.push(sibling);
+            }
+        }
+        SmtProof {
+            key,
+            bitmap,
+            siblings,
+        }
+    }
+
+    fn node(&self, height: usize, prefix: u64) -> Hash {
+        self.nodes
+            .get(&(height, prefix))
+            .copied()
+            .unwrap_or(self.defaults[height])
+    }
+
+    fn set_node(&mut self, height: usize, prefix: u64, hash: Hash) {
+        if hash == self.defaults[height] {
+            self.nodes.remove(&(height, prefix));
+        } else {
+            self.nodes.insert((height, prefix), hash);
+        }
+    }
+
+    /// Hash of the parent of the node at (`height`, `prefix`) and its sibling.
+    fn parent_hash(&self, height: usize, prefix: u64) -> Hash {
+        let left = self.node(height, prefix & !1);
+        let right = self.node(height, prefix | 1);
+        hash_node_with::<H>(&left, &right)
+    }
+}
+
+/// Compact Merkle path for one key.
+///
+/// Bit `h` of `bitmap` is set when the sibling at height `h` is not the
+/// default empty subtree; only those siblings are listed, bottom-up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmtProof {
+    pub key: u64,
+    pub bitmap: u64,
+    pub siblings: Vec<Hash>,
+}
+
+impl SmtProof {
+    /// Root implied by placing `leaf` at this proof's key, or `None` if the
+    /// bitmap and sibling list disagree.
+    pub fn compute_root(&self, leaf: &Hash) -> Option<Hash> {
+        let siblings = self.expanded_siblings(default_hashes::<RollupHasher>())?;
+        let mut node = *leaf;
+        for (height, sibling) in siblings.iter().enumerate() {
+            node = if goes_right(self.key, height) {
+                hash_node(sibling, &node)
+            } else {
+                hash_node(&node, sibling)
+            };
+        }
+        Some(node)
+    }
+
+    /// All `DEPTH` siblings, bottom-up, with the omitted ones filled in.
+    fn expanded_siblings(&self, defaults: &[Hash; DEPTH + 1]) -> Option<[Hash; DEPTH]> {
+        if self.bitmap.count_ones() as usize != self.siblings.len() {
+            return None;
+        }
+        let mut listed = self.siblings.iter();
+        let mut siblings = *defaults.first_chunk::<DEPTH>()?;
+        for (height, sibling) in siblings.iter_mut().enumerate() {
+            if self.bitmap & (1 << height) != 0 {
+                *sibling = *listed.next()?;
+            }
+        }
+        Some(siblings)
+    }
+
+    pub fn verify_inclusion(&self, root: &Hash, leaf: &Hash) -> bool {
+        *leaf != EMPTY_LEAF && self.compute_root(leaf) == Some(*root)
+    }
+
+    /// Proves nothing is stored at `key`.
+    pub fn verify_non_inclusion(&self, root: &Hash) -> bool {
+        self.compute_root(&EMPTY_LEAF) == Some(*root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::hash_leaf;
+
+    fn leaf(n: u64) -> Hash {
+        hash_leaf(&n.to_be_bytes())
+    }
+
+    #[test]
+    fn test_update_and_get() {
+        let mut tree = SparseMerkleTree::new();
+        assert_eq!(tree.root(), empty_root());
+
+        tree.update(5, Some(leaf(5)));
+        tree.update(u64::MAX, Some(leaf(1)));
+        assert_eq!(tree.get(5), Some(leaf(5)));
+        assert_eq!(tree.get(u64::MAX), Some(leaf(1)));
+        assert_eq!(tree.get(6), None);
+        assert_ne!(tree.root(), empty_root());
+
+        tree.update(5, None);
+        tree.update(u64::MAX, None);
+        assert_eq!(tree.root(), empty_root());
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_root_is_independent_of_update_order() {
+        let mut a = SparseMerkleTree::new();
+        let mut b = SparseMerkleTree::new();
+        for key in [0, 1, 2, 1 << 63, 42] {
+            a.update(key, Some(leaf(key)));
+        }
+        for key in [42, 1 << 63, 2, 1, 0] {
+            b.update(key, Some(leaf(key)));
+        }
+        assert_eq!(a.root(), b.root());
+    }
+
+    #[test]
+    fn test_batch_update_matches_sequential_updates() {
+        let keys = [0, 1, 3, 1 << 40, u64::MAX, 7, 7];
+        let mut sequential = SparseMerkleTree::new();
+        for (i, &key) in keys.iter().enumerate() {
+            sequential.update(key, Some(leaf(i as u64)));
+        }
+        sequential.update(3, None);
+
+        let mut batched = SparseMerkleTree::new();
+        batched.update_batch(
+            keys.iter()
+                .enumerate()
+                .map(|(i, &key)| (key, Some(leaf(i as u64)))),
+        );
+        batched.update_batch([(3, None)]);
+
+        assert_eq!(batched.root(), sequential.root());
+        assert_eq!(batched.get(7), Some(leaf(6)));
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        for key in [10, 11, 1 << 50] {
+            tree.update(key, Some(leaf(key)));
+        }
+        let root = tree.root();
+
+        let proof = tree.prove(10);
+        assert!(proof.verify_inclusion(&root, &leaf(10)));
+        assert!(!proof.verify_inclusion(&root, &leaf(11)));
+        assert!(!proof.verify_non_inclusion(&root));
+        // Siblings only for the two populated branches, not all 64 levels
+        assert_eq!(proof.siblings.len(), 2);
+    }
+
+    #[test]
+    fn test_non_inclusion_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        tree.update(10, Some(leaf(10)));
+        let root = tree.root();
+
+        let proof = tree.prove(12);
+        assert!(proof.verify_non_inclusion(&root));
+        assert!(!proof.verify_inclusion(&root, &leaf(12)));
+        assert!(!tree.prove(10).verify_non_inclusion(&root));
+        assert!(
+            SparseMerkleTree::new()
+                .prove(3)
+                .verify_non_inclusion(&empty_root())
+        );
+    }
+
+    #[test]
+    fn test_partial_tree_from_witnesses_tracks_full_tree() {
+        let mut tree = SparseMerkleTree::new();
+        for key in [1, 2, 3, 1 << 33, u64::MAX] {
+            tree.update(key, Some(leaf(key)));
+        }
+        let root = tree.root();
+
+        // Witness one present and one absent key sharing most of their path
+        let proof_a = tree.prove(2);
+        let proof_b = tree.prove(6);
+        let mut partial = <SparseMerkleTree>::from_witnesses(
+            &root,
+            [(&proof_a, Some(leaf(2))), (&proof_b, None)],
+        )
+        .unwrap();
+        assert_eq!(partial.root(), root);
+        assert_eq!(partial.get(2), Some(leaf(2)));
+
+        tree.update_batch([(2, None), (6, Some(leaf(6)))]);
+        partial.update_batch([(2, None), (6, Some(leaf(6)))]);
+        assert_eq!(partial.root(), tree.root());
+    }
+
+    #[test]
+    fn test_partial_tree_rejects_bad_witness() {
+        let mut tree = SparseMerkleTree::new();
+        tree.update(1, Some(leaf(1)));
+        let root = tree.root();
+        let proof = tree.prove(1);
+
+        assert!(<SparseMerkleTree>::from_witnesses(&root, [(&proof, Some(leaf(9)))]).is_none());
+        assert!(<SparseMerkleTree>::from_witnesses(&root, [(&proof, None)]).is_none());
+    }
+
+    #[test]
+    fn test_malformed_proof_is_rejected() {
+        let mut tree = SparseMerkleTree::new();
+        tree.update(1, Some(leaf(1)));
+        tree.update(2, Some(leaf(2)));
+        let root = tree.root();
+
+        let mut proof = tree.prove(1);
+        proof.siblings.push(EMPTY_LEAF);
+        assert_eq!(proof.compute_root(&leaf(1)), None);
+        assert!(!proof.verify_inclusion(&root, &leaf(1)));
+    }
+}
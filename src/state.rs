@@ -0,0 +1,808 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::merkle::{Hash, hash_leaf, hash_node};
+use crate::signature::SignedTransaction;
+use crate::smt::{SmtProof, SparseMerkleTree, empty_root};
+use crate::{Address, AssetId, Balance};
+
+/// The rollup's own asset, which fees are paid in. It has no issuer, so it
+/// can't be minted or burned.
+pub const NATIVE_ASSET: AssetId = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TxKind {
+    /// Moves `amount` of `asset` from `from` to `to`.
+    Transfer,
+    /// Creates `amount` of `asset` for `to`. Only its issuer may mint it;
+    /// minting an asset nobody has issued yet makes `from` its issuer.
+    Mint,
+    /// Destroys `amount` of `asset` held by `from`, which must be its issuer.
+    /// `to` is ignored.
+    Burn,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub kind: TxKind,
+    pub from: Address,
+    pub to: Address,
+    pub asset: AssetId,
+    pub amount: Balance,
+    pub fee: Balance, // in the native asset: the block's base fee is burned, the rest tips its coinbase
+    pub nonce: u64,   // must equal the sender's nonce in the state it's applied to
+}
+
+impl Transaction {
+    /// Canonical bytes a sender signs: every field, big-endian, in order.
+    pub fn encode(&self) -> [u8; 49] {
+        let mut bytes = [0u8; 49];
+        bytes[0] = self.kind as u8;
+        bytes[1..9].copy_from_slice(&self.from.to_be_bytes());
+        bytes[9..17].copy_from_slice(&self.to.to_be_bytes());
+        bytes[17..25].copy_from_slice(&self.asset.to_be_bytes());
+        bytes[25..33].copy_from_slice(&self.amount.to_be_bytes());
+        bytes[33..41].copy_from_slice(&self.fee.to_be_bytes());
+        bytes[41..].copy_from_slice(&self.nonce.to_be_bytes());
+        bytes
+    }
+
+    /// Assets whose balances the transaction may touch, per party: the
+    /// sender's in `asset` and the native asset for the fee, the recipient's
+    /// in `asset`, and the coinbase's native balance.
+    pub fn touched_assets(&self) -> [Vec<AssetId>; 3] {
+        let mut sender = vec![self.asset];
+        if self.asset != NATIVE_ASSET {
+            sender.push(NATIVE_ASSET);
+        }
+        [sender, vec![self.asset], vec![NATIVE_ASSET]]
+    }
+}
+
+/// Block-level inputs to the state transition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockContext {
+    pub coinbase: Address, // L2 account collecting tips
+    pub base_fee: Balance,
+}
+
+/// Why a transaction failed to apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxError {
+    BadSignature,
+    BadNonce,
+    FeeBelowBaseFee,
+    InsufficientBalance,
+    NotIssuer,
+    Overflow,
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxError::BadSignature => write!(f, "not signed by the sender"),
+            TxError::BadNonce => write!(f, "nonce is not the sender's next one"),
+            TxError::FeeBelowBaseFee => write!(f, "fee is below the block's base fee"),
+            TxError::InsufficientBalance => write!(f, "insufficient balance"),
+            TxError::NotIssuer => write!(f, "sender can't mint or burn this asset"),
+            TxError::Overflow => write!(f, "a balance or nonce would overflow"),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub balances: HashMap<(Address, AssetId), Balance>,
+    pub nonces: HashMap<Address, u64>, // transactions applied per sender
+    pub issuers: HashMap<AssetId, Address>,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn balance(&self, address: Address, asset: AssetId) -> Balance {
+        self.balances.get(&(address, asset)).copied().unwrap_or(0)
+    }
+
+    /// Nonce the next transaction from `address` must carry.
+    pub fn nonce(&self, address: Address) -> u64 {
+        self.nonces.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn issuer(&self, asset: AssetId) -> Option<Address> {
+        self.issuers.get(&asset).copied()
+    }
+
+    /// Applies `tx` as included in `block`, if its nonce is the sender's next
+    /// one, its fee covers the base fee, the sender can pay amount and fee,
+    /// and for mints and burns the sender is allowed to issue the asset.
+    /// The base fee is burned and the rest of the fee goes to the coinbase.
+    ///
+    /// All arithmetic is checked, so a transaction that would overflow a
+    /// balance or nonce fails with `TxError::Overflow` in every build rather
+    /// than panicking or wrapping. A failed transaction leaves the state,
+    /// nonce included, untouched, so replaying or skipping ahead always fails.
+    pub fn apply_tx(&mut self, tx: &Transaction, block: &BlockContext) -> Result<(), TxError> {
+        if tx.nonce != self.nonce(tx.from) {
+            return Err(TxError::BadNonce);
+        }
+        if tx.fee < block.base_fee {
+            return Err(TxError::FeeBelowBaseFee);
+        }
+        let allowed = match tx.kind {
+            TxKind::Transfer => true,
+            TxKind::Mint => {
+                tx.asset != NATIVE_ASSET && self.issuer(tx.asset).is_none_or(|i| i == tx.from)
+            }
+            TxKind::Burn => self.issuer(tx.asset) == Some(tx.from),
+        };
+        if !allowed {
+            return Err(TxError::NotIssuer);
+        }
+        let next_nonce = tx.nonce.checked_add(1).ok_or(TxError::Overflow)?;
+
+        // Debits first, so the sender can't spend a tip paid to itself
+        let mut staged = StagedBalances::new(self);
+        staged.debit((tx.from, NATIVE_ASSET), tx.fee)?;
+        match tx.kind {
+            TxKind::Transfer => {
+                staged.debit((tx.from, tx.asset), tx.amount)?;
+                staged.credit((tx.to, tx.asset), tx.amount)?;
+            }
+            TxKind::Mint => staged.credit((tx.to, tx.asset), tx.amount)?,
+            TxKind::Burn => staged.debit((tx.from, tx.asset), tx.amount)?,
+        }
+        staged.credit((block.coinbase, NATIVE_ASSET), tx.fee - block.base_fee)?;
+
+        let balances = staged.balances;
+        self.balances.extend(balances);
+        if tx.kind == TxKind::Mint {
+            self.issuers.insert(tx.asset, tx.from);
+        }
+        self.nonces.insert(tx.from, next_nonce);
+        Ok(())
+    }
+
+    /// The rollup's state transition: `apply_tx`, but only for transactions
+    /// their sender actually signed.
+    pub fn apply_signed_tx(
+        &mut self,
+        signed: &SignedTransaction,
+        block: &BlockContext,
+    ) -> Result<(), TxError> {
+        if !signed.verify_signature() {
+            return Err(TxError::BadSignature);
+        }
+        self.apply_tx(&signed.tx, block)
+    }
+
+    /// Commits to both the accounts tree and the issuer registry.
+    ///
+    /// Zero balances, zero nonces and empty accounts commit the same as
+    /// missing entries, so the root doesn't depend on how the maps were
+    /// populated.
+    pub fn root(&self) -> Hash {
+        state_root(&self.accounts_root(), &self.issuers_root())
+    }
+
+    /// Sparse Merkle root over all accounts, keyed by address. Each account
+    /// commits to its nonce and to a tree of its balances keyed by asset.
+    pub fn accounts_root(&self) -> Hash {
+        self.accounts_tree().root()
+    }
+
+    /// Sparse Merkle root over the issuer of every issued asset.
+    pub fn issuers_root(&self) -> Hash {
+        self.issuers_tree().root()
+    }
+
+    /// Proof of `address`'s nonce and its balances in `assets`, against
+    /// `accounts_root`. Empty accounts and balances get non-inclusion proofs.
+    pub fn prove(&self, address: Address, assets: &[AssetId]) -> AccountProof {
+        let assets_tree = self.assets_tree(address);
+        AccountProof {
+            address,
+            nonce: self.nonce(address),
+            assets_root: assets_tree.root(),
+            proof: self.accounts_tree().prove(address),
+            balances: assets
+                .iter()
+                .map(|&asset| BalanceProof {
+                    asset,
+                    balance: self.balance(address, asset),
+                    proof: assets_tree.prove(asset),
+                })
+                .collect(),
+        }
+    }
+
+    /// Proof of who issues `asset`, against `issuers_root`.
+    pub fn prove_issuer(&self, asset: AssetId) -> IssuerProof {
+        IssuerProof {
+            asset,
+            issuer: self.issuer(asset),
+            proof: self.issuers_tree().prove(asset),
+        }
+    }
+
+    /// `address`'s balances, keyed by asset.
+    fn assets_tree(&self, address: Address) -> SparseMerkleTree {
+        let mut tree = SparseMerkleTree::new();
+        tree.update_batch(
+            self.balances
+                .iter()
+                .filter(|((owner, _), _)| *owner == address)
+                .map(|(&(_, asset), &balance)| (asset, balance_leaf(asset, balance))),
+        );
+        tree
+    }
+
+    fn accounts_tree(&self) -> SparseMerkleTree {
+        let mut assets: HashMap<Address, Vec<(AssetId, Option<Hash>)>> = HashMap::new();
+        for (&(address, asset), &balance) in &self.balances {
+            assets
+                .entry(address)
+                .or_default()
+                .push((asset, balance_leaf(asset, balance)));
+        }
+        for &address in self.nonces.keys() {
+            assets.entry(address).or_default();
+        }
+        let mut tree = SparseMerkleTree::new();
+        tree.update_batch(assets.into_iter().map(|(address, leaves)| {
+            let mut assets_tree = SparseMerkleTree::new();
+            assets_tree.update_batch(leaves);
+            let leaf = account_leaf(address, self.nonce(address), &assets_tree.root());
+            (address, leaf)
+        }));
+        tree
+    }
+
+    fn issuers_tree(&self) -> SparseMerkleTree {
+        let mut tree = SparseMerkleTree::new();
+        tree.update_batch(
+            self.issuers
+                .iter()
+                .map(|(&asset, &issuer)| (asset, issuer_leaf(asset, Some(issuer)))),
+        );
+        tree
+    }
+}
+
+/// Balance changes of one transaction, written back only once every one of
+/// them is known to fit.
+struct StagedBalances<'a> {
+    state: &'a State,
+    balances: HashMap<(Address, AssetId), Balance>,
+}
+
+impl<'a> StagedBalances<'a> {
+    fn new(state: &'a State) -> Self {
+        Self {
+            state,
+            balances: HashMap::new(),
+        }
+    }
+
+    fn balance(&self, (address, asset): (Address, AssetId)) -> Balance {
+        self.balances
+            .get(&(address, asset))
+            .copied()
+            .unwrap_or_else(|| self.state.balance(address, asset))
+    }
+
+    fn debit(&mut self, key: (Address, AssetId), amount: Balance) -> Result<(), TxError> {
+        let balance = self.balance(key).checked_sub(amount);
+        self.balances
+            .insert(key, balance.ok_or(TxError::InsufficientBalance)?);
+        Ok(())
+    }
+
+    fn credit(&mut self, key: (Address, AssetId), amount: Balance) -> Result<(), TxError> {
+        let balance = self.balance(key).checked_add(amount);
+        self.balances.insert(key, balance.ok_or(TxError::Overflow)?);
+        Ok(())
+    }
+}
+
+pub(crate) fn state_root(accounts_root: &Hash, issuers_root: &Hash) -> Hash {
+    hash_node(accounts_root, issuers_root)
+}
+
+pub(crate) fn account_leaf(address: Address, nonce: u64, assets_root: &Hash) -> Option<Hash> {
+    if nonce == 0 && *assets_root == empty_root() {
+        return None;
+    }
+    let mut data = [0u8; 48];
+    data[..8].copy_from_slice(&address.to_be_bytes());
+    data[8..16].copy_from_slice(&nonce.to_be_bytes());
+    data[16..].copy_from_slice(assets_root);
+    Some(hash_leaf(&data))
+}
+
+pub(crate) fn balance_leaf(asset: AssetId, balance: Balance) -> Option<Hash> {
+    if balance == 0 {
+        return None;
+    }
+    let mut data = [0u8; 16];
+    data[..8].copy_from_slice(&asset.to_be_bytes());
+    data[8..].copy_from_slice(&balance.to_be_bytes());
+    Some(hash_leaf(&data))
+}
+
+pub(crate) fn issuer_leaf(asset: AssetId, issuer: Option<Address>) -> Option<Hash> {
+    let issuer = issuer?;
+    let mut data = [0u8; 16];
+    data[..8].copy_from_slice(&asset.to_be_bytes());
+    data[8..].copy_from_slice(&issuer.to_be_bytes());
+    Some(hash_leaf(&data))
+}
+
+/// Proof of one account under an accounts root: its nonce and the root of
+/// its balances, plus proofs of some of those balances against that root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountProof {
+    pub address: Address,
+    pub nonce: u64,
+    pub assets_root: Hash,
+    pub proof: SmtProof,
+    pub balances: Vec<BalanceProof>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalanceProof {
+    pub asset: AssetId,
+    pub balance: Balance,
+    pub proof: SmtProof, // against the account's `assets_root`
+}
+
+impl AccountProof {
+    pub fn verify(&self, accounts_root: &Hash) -> bool {
+        self.proof.key == self.address
+            && verify_leaf(
+                &self.proof,
+                accounts_root,
+                account_leaf(self.address, self.nonce, &self.assets_root),
+            )
+            && self.balances.iter().all(|balance| {
+                balance.proof.key == balance.asset
+                    && verify_leaf(
+                        &balance.proof,
+                        &self.assets_root,
+                        balance_leaf(balance.asset, balance.balance),
+                    )
+            })
+    }
+}
+
+/// Proof of who, if anyone, issues `asset` under an issuers root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuerProof {
+    pub asset: AssetId,
+    pub issuer: Option<Address>,
+    pub proof: SmtProof,
+}
+
+impl IssuerProof {
+    pub fn verify(&self, issuers_root: &Hash) -> bool {
+        self.proof.key == self.asset
+            && verify_leaf(
+                &self.proof,
+                issuers_root,
+                issuer_leaf(self.asset, self.issuer),
+            )
+    }
+}
+
+fn verify_leaf(proof: &SmtProof, root: &Hash, leaf: Option<Hash>) -> bool {
+    match leaf {
+        Some(leaf) => proof.verify_inclusion(root, &leaf),
+        None => proof.verify_non_inclusion(root),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::smt::empty_root;
+    use crate::test_utils::{COINBASE, CONTEXT, addr, setup_state};
+
+    #[test]
+    fn test_root_is_independent_of_insertion_order_and_zero_balances() {
+        let state = setup_state();
+
+        let mut other = State::new();
+        other.balances.insert((addr(9), NATIVE_ASSET), 7);
+        other.balances.insert((addr(4), NATIVE_ASSET), 0);
+        other.balances.insert((addr(2), NATIVE_ASSET), 50);
+        other.balances.insert((addr(1), NATIVE_ASSET), 100);
+
+        assert_eq!(state.root(), other.root());
+        assert_eq!(State::new().accounts_root(), empty_root());
+    }
+
+    #[test]
+    fn test_root_changes_with_balances() {
+        let mut state = setup_state();
+        let before = state.root();
+        assert!(
+            state
+                .apply_tx(
+                    &Transaction {
+                        kind: TxKind::Transfer,
+                        from: addr(1),
+                        to: addr(2),
+                        asset: NATIVE_ASSET,
+                        amount: 1,
+                        fee: 0,
+                        nonce: 0,
+                    },
+                    &CONTEXT
+                )
+                .is_ok()
+        );
+        assert_ne!(state.root(), before);
+    }
+
+    #[test]
+    fn test_account_inclusion_proofs() {
+        let state = setup_state();
+        let root = state.accounts_root();
+
+        for address in [addr(1), addr(2), addr(9)] {
+            let proof = state.prove(address, &[NATIVE_ASSET]);
+            assert_eq!(
+                proof.balances[0].balance,
+                state.balances[&(address, NATIVE_ASSET)]
+            );
+            assert!(proof.verify(&root));
+        }
+
+        let mut forged = state.prove(addr(2), &[NATIVE_ASSET]);
+        forged.balances[0].balance = 5000;
+        assert!(!forged.verify(&root));
+    }
+
+    #[test]
+    fn test_account_non_inclusion_proofs() {
+        let mut state = setup_state();
+        state.balances.insert((addr(4), NATIVE_ASSET), 0);
+        let root = state.accounts_root();
+
+        for address in [addr(3), addr(4), u64::MAX] {
+            let proof = state.prove(address, &[NATIVE_ASSET]);
+            assert_eq!(proof.balances[0].balance, 0);
+            assert!(proof.verify(&root));
+        }
+
+        // Claiming a funded account is empty doesn't verify
+        let mut forged = state.prove(addr(1), &[NATIVE_ASSET]);
+        forged.balances[0].balance = 0;
+        assert!(!forged.verify(&root));
+
+        // Nor does reusing a proof for another address
+        let mut moved = state.prove(addr(3), &[NATIVE_ASSET]);
+        moved.address = addr(5);
+        assert!(!moved.verify(&root));
+    }
+
+    #[test]
+    fn test_nonces_reject_stale_and_gapped_transactions() {
+        let mut state = setup_state();
+        let tx = |nonce| Transaction {
+            kind: TxKind::Transfer,
+            from: addr(1),
+            to: addr(2),
+            asset: NATIVE_ASSET,
+            amount: 10,
+            fee: 0,
+            nonce,
+        };
+
+        assert_eq!(state.apply_tx(&tx(1), &CONTEXT), Err(TxError::BadNonce)); // gap
+        assert!(state.apply_tx(&tx(0), &CONTEXT).is_ok());
+        let after_first = state.root();
+        assert_eq!(state.apply_tx(&tx(0), &CONTEXT), Err(TxError::BadNonce)); // replay
+        assert_eq!(state.root(), after_first);
+        assert!(state.apply_tx(&tx(1), &CONTEXT).is_ok());
+
+        assert_eq!(state.nonce(addr(1)), 2);
+        assert_eq!(state.nonce(addr(2)), 0); // receiving doesn't use up a nonce
+        assert_eq!(state.balance(addr(1), NATIVE_ASSET), 80);
+    }
+
+    #[test]
+    fn test_nonce_is_committed_even_without_balance() {
+        let mut state = setup_state();
+        assert!(
+            state
+                .apply_tx(
+                    &Transaction {
+                        kind: TxKind::Transfer,
+                        from: addr(9),
+                        to: addr(1),
+                        asset: NATIVE_ASSET,
+                        amount: 7,
+                        fee: 0,
+                        nonce: 0,
+                    },
+                    &CONTEXT
+                )
+                .is_ok()
+        );
+        let root = state.accounts_root();
+
+        // Drained account still exists, so replaying from a fresh nonce is provably wrong
+        let proof = state.prove(addr(9), &[NATIVE_ASSET]);
+        assert_eq!((proof.balances[0].balance, proof.nonce), (0, 1));
+        assert!(proof.verify(&root));
+        assert!(!proof.proof.verify_non_inclusion(&root));
+
+        let mut forged = proof.clone();
+        forged.nonce = 0;
+        assert!(!forged.verify(&root));
+    }
+
+    #[test]
+    fn test_fee_is_paid_to_coinbase() {
+        let mut state = setup_state();
+        let tx = |amount, fee, nonce| Transaction {
+            kind: TxKind::Transfer,
+            from: addr(2),
+            to: addr(1),
+            asset: NATIVE_ASSET,
+            amount,
+            fee,
+            nonce,
+        };
+
+        let unaffordable = state.apply_tx(&tx(45, 6, 0), &CONTEXT); // can't cover the fee
+        assert_eq!(unaffordable, Err(TxError::InsufficientBalance));
+        assert!(state.apply_tx(&tx(45, 5, 0), &CONTEXT).is_ok());
+        assert_eq!(state.balance(addr(2), NATIVE_ASSET), 0);
+        assert_eq!(state.balance(addr(1), NATIVE_ASSET), 145);
+        assert_eq!(state.balance(COINBASE, NATIVE_ASSET), 5);
+
+        // Coinbase paying a fee to itself keeps it
+        let own_tx = Transaction {
+            from: COINBASE,
+            ..tx(0, 5, 0)
+        };
+        assert!(state.apply_tx(&own_tx, &CONTEXT).is_ok());
+        assert_eq!(state.balance(COINBASE, NATIVE_ASSET), 5);
+        assert_eq!(state.nonce(COINBASE), 1);
+    }
+
+    const TOKEN: AssetId = 7;
+
+    fn asset_tx(
+        kind: TxKind,
+        from: Address,
+        to: Address,
+        amount: Balance,
+        nonce: u64,
+    ) -> Transaction {
+        Transaction {
+            kind,
+            from,
+            to,
+            asset: TOKEN,
+            amount,
+            fee: 1,
+            nonce,
+        }
+    }
+
+    #[test]
+    fn test_only_the_issuer_mints_and_burns() {
+        let mut state = setup_state();
+        let mut apply = |kind, from, to, amount, nonce| {
+            let tx = asset_tx(kind, addr(from), addr(to), amount, nonce);
+            state.apply_tx(&tx, &CONTEXT)
+        };
+
+        // First mint of an unissued asset makes the sender its issuer
+        assert_eq!(apply(TxKind::Mint, 1, 2, 30, 0), Ok(()));
+        assert_eq!(apply(TxKind::Mint, 2, 2, 30, 0), Err(TxError::NotIssuer));
+
+        // Holders move it like any other asset, paying the fee natively
+        let overdrawn = apply(TxKind::Transfer, 2, 9, 31, 0);
+        assert_eq!(overdrawn, Err(TxError::InsufficientBalance));
+        assert_eq!(apply(TxKind::Transfer, 2, 9, 10, 0), Ok(()));
+
+        // Burning is up to the issuer, and only from what it holds itself
+        assert_eq!(apply(TxKind::Burn, 2, 2, 10, 1), Err(TxError::NotIssuer));
+        let holder_funds = apply(TxKind::Burn, 1, 9, 10, 1);
+        assert_eq!(holder_funds, Err(TxError::InsufficientBalance));
+        assert_eq!(apply(TxKind::Transfer, 2, 1, 10, 1), Ok(()));
+        let overburned = apply(TxKind::Burn, 1, 1, 11, 1);
+        assert_eq!(overburned, Err(TxError::InsufficientBalance));
+        assert_eq!(apply(TxKind::Burn, 1, 9, 10, 1), Ok(()));
+
+        assert_eq!(state.issuer(TOKEN), Some(addr(1)));
+        assert_eq!(state.balance(addr(1), TOKEN), 0);
+        assert_eq!(state.balance(addr(2), TOKEN), 10);
+        assert_eq!(state.balance(addr(2), NATIVE_ASSET), 48);
+        assert_eq!(state.balance(addr(9), TOKEN), 10);
+        assert_eq!(state.balance(addr(9), NATIVE_ASSET), 7);
+
+        // The native asset has no issuer
+        let native = Transaction {
+            asset: NATIVE_ASSET,
+            ..asset_tx(TxKind::Mint, addr(1), addr(1), 1000, 2)
+        };
+        assert_eq!(state.apply_tx(&native, &CONTEXT), Err(TxError::NotIssuer));
+    }
+
+    #[test]
+    fn test_balances_are_proven_per_asset() {
+        let mut state = setup_state();
+        assert!(
+            state
+                .apply_tx(&asset_tx(TxKind::Mint, addr(1), addr(2), 30, 0), &CONTEXT)
+                .is_ok()
+        );
+        let root = state.accounts_root();
+
+        let proof = state.prove(addr(2), &[NATIVE_ASSET, TOKEN, 8]);
+        let balances: Vec<_> = proof.balances.iter().map(|b| b.balance).collect();
+        assert_eq!(balances, [50, 30, 0]);
+        assert!(proof.verify(&root));
+
+        // A balance of one asset doesn't pass for another
+        let mut swapped = proof.clone();
+        swapped.balances[1].asset = NATIVE_ASSET;
+        assert!(!swapped.verify(&root));
+
+        let issuer = state.prove_issuer(TOKEN);
+        assert_eq!(issuer.issuer, Some(addr(1)));
+        assert!(issuer.verify(&state.issuers_root()));
+        let mut usurped = issuer.clone();
+        usurped.issuer = Some(addr(2));
+        assert!(!usurped.verify(&state.issuers_root()));
+        assert!(state.prove_issuer(8).verify(&state.issuers_root()));
+    }
+
+    #[test]
+    fn test_overflow_fails_instead_of_wrapping() {
+        let mut state = setup_state();
+        state.balances.insert((addr(3), NATIVE_ASSET), Balance::MAX);
+        state
+            .balances
+            .insert((COINBASE, NATIVE_ASSET), Balance::MAX);
+        state.nonces.insert(addr(9), u64::MAX);
+        let root = state.root();
+        let tx = |from, to, amount, fee, nonce| Transaction {
+            kind: TxKind::Transfer,
+            from: addr(from),
+            to: addr(to),
+            asset: NATIVE_ASSET,
+            amount,
+            fee,
+            nonce,
+        };
+
+        // Recipient, coinbase and nonce each at their limit
+        assert_eq!(
+            state.apply_tx(&tx(1, 3, 1, 0, 0), &CONTEXT),
+            Err(TxError::Overflow)
+        );
+        assert_eq!(
+            state.apply_tx(&tx(1, 2, 1, 1, 0), &CONTEXT),
+            Err(TxError::Overflow)
+        );
+        assert_eq!(
+            state.apply_tx(&tx(9, 2, 1, 0, u64::MAX), &CONTEXT),
+            Err(TxError::Overflow)
+        );
+        let mint = Transaction {
+            kind: TxKind::Mint,
+            asset: 7,
+            ..tx(3, 2, Balance::MAX, 0, 0)
+        };
+        assert_eq!(state.apply_tx(&mint, &CONTEXT), Ok(()));
+        let mint_more = Transaction { nonce: 1, ..mint };
+        assert_eq!(state.apply_tx(&mint_more, &CONTEXT), Err(TxError::Overflow));
+
+        // Nothing but the successful mint touched the state
+        assert_eq!(state.balance(addr(2), 7), Balance::MAX);
+        state.balances.remove(&(addr(2), 7));
+        state.issuers.clear();
+        state.nonces.remove(&addr(3));
+        assert_eq!(state.root(), root);
+
+        // Amount and fee together can't wrap around to something affordable
+        let wrapping = tx(1, 2, Balance::MAX, 2, 0);
+        assert_eq!(
+            state.apply_tx(&wrapping, &CONTEXT),
+            Err(TxError::InsufficientBalance)
+        );
+    }
+
+    fn supply(state: &State, asset: AssetId) -> u128 {
+        state
+            .balances
+            .iter()
+            .filter(|((_, a), _)| *a == asset)
+            .map(|(_, &balance)| balance as u128)
+            .sum()
+    }
+
+    fn any_amount() -> impl Strategy<Value = Balance> {
+        prop_oneof![0..200u64, Balance::MAX - 200..=Balance::MAX]
+    }
+
+    fn any_tx() -> impl Strategy<Value = Transaction> {
+        let kind = prop_oneof![
+            4 => Just(TxKind::Transfer),
+            1 => Just(TxKind::Mint),
+            1 => Just(TxKind::Burn),
+        ];
+        (
+            kind,
+            1..=4u64,
+            1..=5u64,
+            0..=2u64,
+            any_amount(),
+            0..20u64,
+            0..3u64,
+        )
+            .prop_map(|(kind, from, to, asset, amount, fee, nonce)| Transaction {
+                kind,
+                from,
+                to,
+                asset,
+                amount,
+                fee,
+                nonce,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_accepted_transactions_conserve_supply(
+            genesis in prop::collection::vec(((1..=5u64, 0..=2u64), any_amount()), 0..10),
+            txs in prop::collection::vec(any_tx(), 1..30),
+            coinbase in 1..=5u64,
+            base_fee in 0..5u64,
+        ) {
+            let mut state = State::new();
+            state.balances.extend(genesis);
+            let block = BlockContext { coinbase, base_fee };
+
+            for tx in &txs {
+                let before = state.clone();
+                match state.apply_tx(tx, &block) {
+                    Ok(()) => {
+                        for asset in 0..=2 {
+                            let expected = match (tx.kind, asset) {
+                                (_, NATIVE_ASSET) => supply(&before, asset) - base_fee as u128,
+                                (TxKind::Mint, a) if a == tx.asset => {
+                                    supply(&before, asset) + tx.amount as u128
+                                }
+                                (TxKind::Burn, a) if a == tx.asset => {
+                                    supply(&before, asset) - tx.amount as u128
+                                }
+                                _ => supply(&before, asset),
+                            };
+                            prop_assert_eq!(supply(&state, asset), expected);
+                        }
+                        prop_assert_eq!(state.nonce(tx.from), before.nonce(tx.from) + 1);
+                    }
+                    Err(_) => {
+                        prop_assert_eq!(&state.balances, &before.balances);
+                        prop_assert_eq!(&state.nonces, &before.nonces);
+                        prop_assert_eq!(&state.issuers, &before.issuers);
+                    }
+                }
+            }
+        }
+    }
+}
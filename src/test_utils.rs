@@ -0,0 +1,38 @@
+// Fixtures shared by the unit tests
+use crate::signature::{Keypair, SignedTransaction};
+use crate::state::{BlockContext, NATIVE_ASSET, State, Transaction, TxKind};
+use crate::{Address, Balance};
+
+pub const COINBASE: Address = 5;
+pub const CONTEXT: BlockContext = BlockContext {
+    coinbase: COINBASE,
+    base_fee: 0,
+};
+
+pub fn key(n: u8) -> Keypair {
+    Keypair::from_seed(&[n; 32])
+}
+
+pub fn addr(n: u8) -> Address {
+    key(n).address()
+}
+
+pub fn transfer(from: u8, to: u8, amount: Balance, nonce: u64) -> SignedTransaction {
+    key(from).sign(Transaction {
+        kind: TxKind::Transfer,
+        from: addr(from),
+        to: addr(to),
+        asset: NATIVE_ASSET,
+        amount,
+        fee: 0,
+        nonce,
+    })
+}
+
+pub fn setup_state() -> State {
+    let mut state = State::new();
+    state.balances.insert((addr(1), NATIVE_ASSET), 100);
+    state.balances.insert((addr(2), NATIVE_ASSET), 50);
+    state.balances.insert((addr(9), NATIVE_ASSET), 7);
+    state
+}
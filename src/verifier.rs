@@ -0,0 +1,2510 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::block::{BlockHeader, GENESIS_PARENT_HASH, state_roots_root, transactions_root};
+use crate::dispute::{Dispute, Party};
+use crate::fee_market::FeeMarket;
+use crate::fraud_proof::{FraudProof, Verdict};
+use crate::ledger::{BondId, Ledger};
+use crate::merkle::Hash;
+use crate::receipt::{Receipt, receipts_root};
+use crate::signature::{SignatureBytes, SignedTransaction, invalid_signatures};
+use crate::state::BlockContext;
+use crate::{Address, Balance, BlockNumber};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollupBlock {
+    pub block_number: BlockNumber,
+    pub parent_hash: Hash, // hash of the previous block's header
+    pub sequencer: Address,
+    pub coinbase: Address,    // L2 account credited with the block's tips
+    pub base_fee: Balance,    // burned per transaction; set by the fee market from the parent block
+    pub timestamp: u64,       // L2 time the sequencer built the block at
+    pub pre_state_root: Hash, // the parent's post-state root
+    pub transactions: Vec<SignedTransaction>,
+    pub aggregate_signature: SignatureBytes, // all of the transactions' signatures combined
+    pub state_roots: Vec<Hash>, // state root after each tx; the last one is the block's post-state root
+    pub receipts: Vec<Receipt>, // outcome of each tx, committed to by `receipts_root`
+    pub submitted_at: u64,      // L1 time, set on submission
+    pub status: BlockStatus,
+}
+
+impl RollupBlock {
+    /// What the block's transactions are executed against besides the state.
+    pub fn context(&self) -> BlockContext {
+        BlockContext {
+            coinbase: self.coinbase,
+            base_fee: self.base_fee,
+        }
+    }
+
+    pub fn receipts_root(&self) -> Hash {
+        receipts_root(&self.receipts)
+    }
+
+    /// State root after the block's last transaction, or its pre-state root
+    /// if it has none.
+    pub fn post_state_root(&self) -> Hash {
+        self.state_roots
+            .last()
+            .copied()
+            .unwrap_or(self.pre_state_root)
+    }
+
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            block_number: self.block_number,
+            parent_hash: self.parent_hash,
+            sequencer: self.sequencer,
+            coinbase: self.coinbase,
+            base_fee: self.base_fee,
+            timestamp: self.timestamp,
+            tx_count: self.transactions.len(),
+            pre_state_root: self.pre_state_root,
+            post_state_root: self.post_state_root(),
+            state_roots_root: state_roots_root(&self.state_roots),
+            transactions_root: transactions_root(&self.transactions),
+            receipts_root: self.receipts_root(),
+        }
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.header().hash()
+    }
+}
+
+/// Pending blocks can be challenged until their window closes, then they
+/// become final; fraud reverts them instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockStatus {
+    Pending,
+    Finalized,
+    Reverted,
+}
+
+/// Whether a block may include transactions that fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailedTxPolicy {
+    /// A failing transaction is a no-op recorded with a failure receipt.
+    #[default]
+    Record,
+    /// A block including a failing transaction is fraudulent; a failure
+    /// receipt is the sequencer's own proof of it.
+    Reject,
+}
+
+/// Why the verifier refused a block, challenge or dispute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifierError {
+    UnknownBlock(BlockNumber),
+    NonSequentialBlock {
+        expected: BlockNumber,
+        got: BlockNumber,
+    },
+    TxIndexOutOfRange {
+        block_number: BlockNumber,
+        tx_index: usize,
+    },
+    EmptyBlock(BlockNumber),
+    UnknownParent(BlockNumber),
+    WrongPreStateRoot(BlockNumber),
+    WrongTimestamp {
+        block_number: BlockNumber,
+        timestamp: u64,
+        min: u64, // the parent's
+        max: u64, // L1's current time
+    },
+    CommitmentCountMismatch {
+        block_number: BlockNumber,
+        tx_count: usize,
+        state_roots: usize,
+        receipts: usize,
+    },
+    BlockTooLarge {
+        block_number: BlockNumber,
+        tx_count: usize,
+        max: usize,
+    },
+    WrongBaseFee {
+        block_number: BlockNumber,
+        expected: Balance,
+        got: Balance,
+    },
+    InvalidSignature {
+        block_number: BlockNumber,
+        tx_index: usize,
+    },
+    DuplicateChallenge,
+    OutsideChallengeWindow(BlockNumber),
+    BalanceOverflow(Address),
+    UnauthorizedCaller {
+        caller: Address,
+        account: Address,
+    },
+    InsufficientBond {
+        owner: Address,
+        required: Balance,
+    },
+}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifierError::UnknownBlock(number) => write!(f, "unknown block #{}", number),
+            VerifierError::NonSequentialBlock { expected, got } => {
+                write!(f, "expected block #{}, got #{}", expected, got)
+            }
+            VerifierError::TxIndexOutOfRange {
+                block_number,
+                tx_index,
+            } => write!(f, "block #{} has no tx[{}]", block_number, tx_index),
+            VerifierError::EmptyBlock(number) => {
+                write!(f, "block #{} has no transactions to dispute", number)
+            }
+            VerifierError::UnknownParent(number) => {
+                write!(f, "block #{} doesn't build on the current tip", number)
+            }
+            VerifierError::WrongTimestamp {
+                block_number,
+                timestamp,
+                min,
+                max,
+            } => write!(
+                f,
+                "block #{} has timestamp {} outside {}..={}",
+                block_number, timestamp, min, max
+            ),
+            VerifierError::WrongPreStateRoot(number) => {
+                write!(
+                    f,
+                    "block #{} doesn't start from the tip's state root",
+                    number
+                )
+            }
+            VerifierError::CommitmentCountMismatch {
+                block_number,
+                tx_count,
+                state_roots,
+                receipts,
+            } => write!(
+                f,
+                "block #{} has {} transactions but {} state roots and {} receipts",
+                block_number, tx_count, state_roots, receipts
+            ),
+            VerifierError::BlockTooLarge {
+                block_number,
+                tx_count,
+                max,
+            } => write!(
+                f,
+                "block #{} has {} transactions, at most {} fit",
+                block_number, tx_count, max
+            ),
+            VerifierError::WrongBaseFee {
+                block_number,
+                expected,
+                got,
+            } => write!(
+                f,
+                "block #{} has base fee {}, expected {}",
+                block_number, got, expected
+            ),
+            VerifierError::InvalidSignature {
+                block_number,
+                tx_index,
+            } => write!(
+                f,
+                "block #{} tx[{}] is not signed by its sender",
+                block_number, tx_index
+            ),
+            VerifierError::DuplicateChallenge => write!(f, "challenge is already pending"),
+            VerifierError::OutsideChallengeWindow(number) => {
+                write!(f, "block #{} is outside its challenge window", number)
+            }
+            VerifierError::BalanceOverflow(owner) => {
+                write!(f, "{}'s L1 funds would overflow", owner)
+            }
+            VerifierError::UnauthorizedCaller { caller, account } => {
+                write!(f, "{} can't act for {}", caller, account)
+            }
+            VerifierError::InsufficientBond { owner, required } => {
+                write!(f, "{} can't post a bond of {}", owner, required)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifierError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockEvent {
+    Finalized(BlockNumber),
+    Reverted(BlockNumber),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FraudChallenge {
+    pub block_number: BlockNumber,
+    pub tx_index: usize,
+    pub challenger: Address,
+    pub time: u64,
+    pub proof: FraudProof,
+    pub valid: Option<bool>,
+}
+
+impl FraudChallenge {
+    pub fn bond_id(&self) -> BondId {
+        BondId::Challenge {
+            block_number: self.block_number,
+            tx_index: self.tx_index,
+            challenger: self.challenger,
+        }
+    }
+}
+
+pub struct L1Verifier {
+    time: u64,
+    blocks: Vec<RollupBlock>,
+    reverted_blocks: Vec<RollupBlock>,
+    events: Vec<BlockEvent>,
+    challenges: VecDeque<FraudChallenge>,
+    resolved_challenges: Vec<FraudChallenge>,
+    disputes: Vec<Dispute>, // indexed by dispute id
+    challenge_timeout: u64, // challenge window per block; also the time each side gets per dispute move
+    genesis_root: Hash,     // L2 state root committed on L1 before block #0
+    ledger: Ledger,
+    block_bond: Balance,
+    challenge_bond: Balance, // per fraud challenge or dispute
+    fee_market: FeeMarket,
+    failed_tx_policy: FailedTxPolicy,
+}
+
+impl L1Verifier {
+    pub fn new(timeout: u64, genesis_root: Hash) -> Self {
+        Self {
+            time: 0,
+            blocks: vec![],
+            reverted_blocks: vec![],
+            events: vec![],
+            challenges: VecDeque::new(),
+            resolved_challenges: vec![],
+            disputes: vec![],
+            challenge_timeout: timeout,
+            genesis_root,
+            ledger: Ledger::new(),
+            block_bond: 0,
+            challenge_bond: 0,
+            fee_market: FeeMarket::default(),
+            failed_tx_policy: FailedTxPolicy::default(),
+        }
+    }
+
+    /// Requires sequencers to lock `block_bond` per block and challengers
+    /// `challenge_bond` per challenge or dispute.
+    pub fn with_bonds(mut self, block_bond: Balance, challenge_bond: Balance) -> Self {
+        self.block_bond = block_bond;
+        self.challenge_bond = challenge_bond;
+        self
+    }
+
+    /// Prices block space with `fee_market` instead of the default, which
+    /// starts at a zero base fee. Panics if its target is zero, which would
+    /// leave no room for transactions and no fullness to price.
+    pub fn with_fee_market(mut self, fee_market: FeeMarket) -> Self {
+        assert!(
+            fee_market.target_txs > 0,
+            "fee market needs a nonzero target"
+        );
+        self.fee_market = fee_market;
+        self
+    }
+
+    /// Judges blocks with failing transactions by `policy` instead of the
+    /// default, which records them as no-ops.
+    pub fn with_failed_tx_policy(mut self, policy: FailedTxPolicy) -> Self {
+        self.failed_tx_policy = policy;
+        self
+    }
+
+    /// Base fee the next block must carry, following from the valid tip.
+    pub fn next_base_fee(&self) -> Balance {
+        match self.blocks.last() {
+            Some(parent) => self
+                .fee_market
+                .next_base_fee(parent.base_fee, parent.transactions.len()),
+            None => self.fee_market.initial_base_fee,
+        }
+    }
+
+    /// Most transactions the next block may hold.
+    pub fn max_txs(&self) -> usize {
+        self.fee_market.max_txs()
+    }
+
+    /// Number the next block must carry to extend the valid chain.
+    pub fn next_block_number(&self) -> BlockNumber {
+        self.blocks.len() as BlockNumber
+    }
+
+    /// Hash of the valid chain's last block, which the next block names as its parent.
+    pub fn tip_hash(&self) -> Hash {
+        self.blocks
+            .last()
+            .map(RollupBlock::hash)
+            .unwrap_or(GENESIS_PARENT_HASH)
+    }
+
+    /// Post-state root of the valid chain, which the next block builds on.
+    pub fn tip_state_root(&self) -> Hash {
+        self.blocks
+            .last()
+            .map(RollupBlock::post_state_root)
+            .unwrap_or(self.genesis_root)
+    }
+
+    pub fn events(&self) -> &[BlockEvent] {
+        &self.events
+    }
+
+    /// Most recent block that can no longer be challenged.
+    pub fn latest_finalized_block(&self) -> Option<&RollupBlock> {
+        self.blocks
+            .iter()
+            .rev()
+            .find(|block| block.status == BlockStatus::Finalized)
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Credits L1 funds that `owner` can later lock as bonds.
+    pub fn deposit(&mut self, owner: Address, amount: Balance) -> Result<(), VerifierError> {
+        if !self.ledger.deposit(owner, amount) {
+            return Err(VerifierError::BalanceOverflow(owner));
+        }
+        Ok(())
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Accepts `block` if it names the valid chain's tip as its parent and
+    /// starts from the tip's state root, is timestamped no earlier than its
+    /// parent and no later than now, commits to a state root and receipt per
+    /// transaction, fits within the fee market's capacity and base fee,
+    /// every transaction in it is signed by its sender
+    /// and its sequencer, who must be the `caller`, can lock the block bond,
+    /// opening its challenge window from now.
+    ///
+    /// Signatures are checked in one pass against the block's aggregate;
+    /// only if that fails is each transaction checked on its own.
+    pub fn submit_block(
+        &mut self,
+        caller: Address,
+        mut block: RollupBlock,
+    ) -> Result<(), VerifierError> {
+        check_caller(caller, block.sequencer)?;
+        let expected = self.next_block_number();
+        if block.block_number != expected {
+            return Err(VerifierError::NonSequentialBlock {
+                expected,
+                got: block.block_number,
+            });
+        }
+        if block.parent_hash != self.tip_hash() {
+            return Err(VerifierError::UnknownParent(block.block_number));
+        }
+        if block.pre_state_root != self.tip_state_root() {
+            return Err(VerifierError::WrongPreStateRoot(block.block_number));
+        }
+        let min = self.blocks.last().map_or(0, |parent| parent.timestamp);
+        if block.timestamp < min || block.timestamp > self.time {
+            return Err(VerifierError::WrongTimestamp {
+                block_number: block.block_number,
+                timestamp: block.timestamp,
+                min,
+                max: self.time,
+            });
+        }
+        // Every root and receipt must belong to a step that can be challenged
+        let tx_count = block.transactions.len();
+        if block.state_roots.len() != tx_count || block.receipts.len() != tx_count {
+            return Err(VerifierError::CommitmentCountMismatch {
+                block_number: block.block_number,
+                tx_count,
+                state_roots: block.state_roots.len(),
+                receipts: block.receipts.len(),
+            });
+        }
+        let max = self.fee_market.max_txs();
+        if block.transactions.len() > max {
+            return Err(VerifierError::BlockTooLarge {
+                block_number: block.block_number,
+                tx_count: block.transactions.len(),
+                max,
+            });
+        }
+        let expected = self.next_base_fee();
+        if block.base_fee != expected {
+            return Err(VerifierError::WrongBaseFee {
+                block_number: block.block_number,
+                expected,
+                got: block.base_fee,
+            });
+        }
+        if let Some(&tx_index) =
+            invalid_signatures(&block.transactions, &block.aggregate_signature).first()
+        {
+            return Err(VerifierError::InvalidSignature {
+                block_number: block.block_number,
+                tx_index,
+            });
+        }
+        self.post_bond(
+            BondId::Block(block.block_number),
+            block.sequencer,
+            self.block_bond,
+        )?;
+        println!("Block #{} submitted", block.block_number);
+        block.submitted_at = self.time;
+        block.status = BlockStatus::Pending;
+        self.blocks.push(block);
+        Ok(())
+    }
+
+    /// Queues `challenge` if it targets a transaction of a block still in its
+    /// challenge window and the challenger, who must be the `caller`, can
+    /// lock the challenge bond. Its resolution delay runs from now, whatever
+    /// time it was dated with.
+    pub fn submit_challenge(
+        &mut self,
+        caller: Address,
+        mut challenge: FraudChallenge,
+    ) -> Result<(), VerifierError> {
+        check_caller(caller, challenge.challenger)?;
+        let block = self.challengeable_block(challenge.block_number)?;
+        if challenge.tx_index >= block.transactions.len() {
+            return Err(VerifierError::TxIndexOutOfRange {
+                block_number: challenge.block_number,
+                tx_index: challenge.tx_index,
+            });
+        }
+        if self
+            .challenges
+            .iter()
+            .any(|pending| pending.bond_id() == challenge.bond_id())
+        {
+            return Err(VerifierError::DuplicateChallenge);
+        }
+        self.post_bond(
+            challenge.bond_id(),
+            challenge.challenger,
+            self.challenge_bond,
+        )?;
+        println!(
+            "Fraud challenge submitted on block #{} tx[{}] by {}",
+            challenge.block_number, challenge.tx_index, challenge.challenger
+        );
+        challenge.time = self.time;
+        self.challenges.push_back(challenge);
+        Ok(())
+    }
+
+    pub fn advance_time(&mut self, ticks: u64) {
+        self.time = self.time.saturating_add(ticks);
+        println!("Advanced L1 time by {} ticks", ticks);
+        self.process_challenges();
+        self.process_dispute_timeouts();
+        self.finalize_blocks();
+    }
+
+    pub fn dispute(&self, id: usize) -> Option<&Dispute> {
+        self.disputes.get(id)
+    }
+
+    /// Starts a bisection game over a whole block and returns its id. The
+    /// `challenger` is the account calling, and the dispute bond is locked
+    /// from it.
+    pub fn open_dispute(
+        &mut self,
+        block_number: BlockNumber,
+        challenger: Address,
+    ) -> Result<usize, VerifierError> {
+        let tx_count = self.challengeable_block(block_number)?.transactions.len();
+        if tx_count == 0 {
+            return Err(VerifierError::EmptyBlock(block_number));
+        }
+        let id = self.disputes.len();
+        self.post_bond(BondId::Dispute(id), challenger, self.challenge_bond)?;
+        println!(
+            "Dispute opened on block #{} by {}",
+            block_number, challenger
+        );
+        let deadline = self.time.saturating_add(self.challenge_timeout);
+        self.disputes
+            .push(Dispute::open(block_number, challenger, tx_count, deadline));
+        Ok(id)
+    }
+
+    /// Proposer's bisection move. The block already commits to every
+    /// intermediate root, so this restates the one at the midpoint; posting
+    /// anything else is equivocation and loses the dispute on the spot. Only
+    /// the block's sequencer may move.
+    pub fn bisect_dispute(&mut self, id: usize, proposer: Address, midpoint_root: Hash) -> bool {
+        let Some(dispute) = self.disputes.get(id) else {
+            return false;
+        };
+        if !dispute.is_active()
+            || dispute.turn != Party::Proposer
+            || !self.is_proposer(dispute, proposer)
+            || dispute.disputed_tx().is_some()
+        {
+            return false;
+        }
+        if self.root_at_step(dispute.block_number, dispute.midpoint()) != Some(midpoint_root) {
+            self.settle_dispute(id, Party::Challenger);
+            return false;
+        }
+        let deadline = self.time.saturating_add(self.challenge_timeout);
+        self.disputes[id].bisect(deadline);
+        true
+    }
+
+    /// Challenger's answer to the proposer's last midpoint. Only the account
+    /// that opened the dispute may give it.
+    pub fn respond_to_bisection(
+        &mut self,
+        id: usize,
+        responder: Address,
+        agrees_with_midpoint: bool,
+    ) -> bool {
+        let deadline = self.time.saturating_add(self.challenge_timeout);
+        match self.disputes.get_mut(id) {
+            Some(dispute)
+                if dispute.is_active()
+                    && dispute.turn == Party::Challenger
+                    && dispute.challenger == responder =>
+            {
+                dispute.respond(agrees_with_midpoint, deadline);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Proposer's last move once one transaction is left: a witness proof
+    /// that it takes the agreed root to the disputed one. A proof that
+    /// doesn't check out is rejected and the proposer may retry until its
+    /// deadline; one that exposes fraud settles the dispute against it. Only
+    /// the block's sequencer may defend it.
+    pub fn defend_step(&mut self, id: usize, proposer: Address, proof: &FraudProof) -> bool {
+        let Some(dispute) = self.disputes.get(id) else {
+            return false;
+        };
+        let Some(tx_index) = dispute.disputed_tx() else {
+            return false;
+        };
+        if !dispute.is_active()
+            || dispute.turn != Party::Proposer
+            || !self.is_proposer(dispute, proposer)
+        {
+            return false;
+        }
+        let header = self.blocks[dispute.block_number as usize].header();
+        if Some(proof.pre_state_root) != self.root_at_step(dispute.block_number, dispute.agreed)
+            || Some(proof.post_state_root)
+                != self.root_at_step(dispute.block_number, dispute.disputed)
+            || !proof.proves_inclusion(&header, tx_index)
+        {
+            return false;
+        }
+        match self.judge_step(proof, &header.context()) {
+            Verdict::Valid => self.settle_dispute(id, Party::Proposer),
+            Verdict::Fraud => self.settle_dispute(id, Party::Challenger),
+            Verdict::InvalidWitness => return false,
+        }
+        true
+    }
+
+    fn is_proposer(&self, dispute: &Dispute, address: Address) -> bool {
+        self.blocks
+            .get(dispute.block_number as usize)
+            .is_some_and(|block| block.sequencer == address)
+    }
+
+    fn process_dispute_timeouts(&mut self) {
+        for id in 0..self.disputes.len() {
+            let dispute = &self.disputes[id];
+            if dispute.is_active() && self.time > dispute.deadline {
+                println!("Dispute #{} timed out waiting on {:?}", id, dispute.turn);
+                self.settle_dispute(id, dispute.turn.opponent());
+            }
+        }
+    }
+
+    fn settle_dispute(&mut self, id: usize, winner: Party) {
+        let dispute = &mut self.disputes[id];
+        dispute.winner = Some(winner);
+        let (block_number, challenger) = (dispute.block_number, dispute.challenger);
+        match winner {
+            Party::Proposer => {
+                println!(
+                    "Dispute #{} resolved: ✅ VALID block at #{}",
+                    id, block_number
+                );
+                let sequencer = self.blocks[block_number as usize].sequencer;
+                self.ledger.slash(BondId::Dispute(id), sequencer);
+            }
+            Party::Challenger => {
+                println!(
+                    "Dispute #{} resolved: ❌ FRAUD detected at block #{}",
+                    id, block_number
+                );
+                self.revert_fraudulent_block(block_number, challenger);
+                self.ledger.refund(BondId::Dispute(id));
+            }
+        }
+    }
+
+    fn process_challenges(&mut self) {
+        while let Some(mut challenge) = self.challenges.front().cloned() {
+            if self.time.saturating_sub(challenge.time) >= self.challenge_timeout {
+                let _ = self.challenges.pop_front();
+                // Checked on submission, and reverting a block drops its challenges
+                let header = self.blocks[challenge.block_number as usize].header();
+
+                // The challenger's witnesses stand in for the state, so only tx[i] is re-executed
+                // A proof about some other transition says nothing about this block
+                let proof = &challenge.proof;
+                let valid = !proof.proves_inclusion(&header, challenge.tx_index)
+                    || self.judge_step(proof, &header.context()) != Verdict::Fraud;
+
+                challenge.valid = Some(valid);
+                self.resolved_challenges.push(challenge.clone());
+
+                if valid {
+                    println!(
+                        "Challenge resolved: ✅ VALID block at #{} tx[{}]",
+                        challenge.block_number, challenge.tx_index
+                    );
+                    let sequencer = self.blocks[challenge.block_number as usize].sequencer;
+                    self.ledger.slash(challenge.bond_id(), sequencer);
+                } else {
+                    println!(
+                        "Challenge resolved: ❌ FRAUD detected at block #{} tx[{}]",
+                        challenge.block_number, challenge.tx_index
+                    );
+                    self.revert_fraudulent_block(challenge.block_number, challenge.challenger);
+                    self.ledger.refund(challenge.bond_id());
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Re-executes the proven transaction over `proof`'s witnesses, then
+    /// applies the failed-transaction policy to a step that checks out.
+    fn judge_step(&self, proof: &FraudProof, block: &BlockContext) -> Verdict {
+        match proof.verify(block) {
+            Verdict::Valid
+                if self.failed_tx_policy == FailedTxPolicy::Reject
+                    && !proof.receipt.is_success() =>
+            {
+                Verdict::Fraud
+            }
+            verdict => verdict,
+        }
+    }
+
+    /// Slashes the fraudulent block's bond to `challenger` and rolls the
+    /// chain back to its parent. Every descendant built on its post-state is
+    /// reverted too, with bonds refunded to their sequencers, and pending
+    /// challenges or disputes against any reverted block are dropped with
+    /// bonds refunded to their challengers.
+    fn revert_fraudulent_block(&mut self, block_number: BlockNumber, challenger: Address) {
+        self.ledger.slash(BondId::Block(block_number), challenger);
+
+        for mut block in self.blocks.split_off(block_number as usize) {
+            println!("Block #{} reverted", block.block_number);
+            block.status = BlockStatus::Reverted;
+            self.events.push(BlockEvent::Reverted(block.block_number));
+            self.ledger.refund(BondId::Block(block.block_number));
+            self.reverted_blocks.push(block);
+        }
+
+        let (dropped, pending) = self
+            .challenges
+            .drain(..)
+            .partition(|challenge| challenge.block_number >= block_number);
+        self.challenges = pending;
+        for challenge in dropped {
+            println!(
+                "Fraud challenge on reverted block #{} tx[{}] dropped",
+                challenge.block_number, challenge.tx_index
+            );
+            self.ledger.refund(challenge.bond_id());
+        }
+
+        for (id, dispute) in self.disputes.iter_mut().enumerate() {
+            if dispute.is_active() && dispute.block_number >= block_number {
+                println!(
+                    "Dispute #{} on reverted block #{} dropped",
+                    id, dispute.block_number
+                );
+                dispute.cancelled = true;
+                self.ledger.refund(BondId::Dispute(id));
+            }
+        }
+    }
+
+    fn challengeable_block(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<&RollupBlock, VerifierError> {
+        let block = self
+            .blocks
+            .get(block_number as usize)
+            .ok_or(VerifierError::UnknownBlock(block_number))?;
+        if block.status != BlockStatus::Pending
+            || self.time >= block.submitted_at.saturating_add(self.challenge_timeout)
+        {
+            return Err(VerifierError::OutsideChallengeWindow(block_number));
+        }
+        Ok(block)
+    }
+
+    fn post_bond(
+        &mut self,
+        id: BondId,
+        owner: Address,
+        amount: Balance,
+    ) -> Result<(), VerifierError> {
+        if self.ledger.post_bond(id, owner, amount) {
+            Ok(())
+        } else {
+            Err(VerifierError::InsufficientBond {
+                owner,
+                required: amount,
+            })
+        }
+    }
+
+    /// Finalizes pending blocks in order once their window has passed with
+    /// nothing left open against them, returning their bonds.
+    fn finalize_blocks(&mut self) {
+        for block in &mut self.blocks {
+            if block.status == BlockStatus::Finalized {
+                continue;
+            }
+            let number = block.block_number;
+            let contested = self.challenges.iter().any(|c| c.block_number == number)
+                || self
+                    .disputes
+                    .iter()
+                    .any(|d| d.is_active() && d.block_number == number);
+            if contested || self.time < block.submitted_at.saturating_add(self.challenge_timeout) {
+                break; // children can't finalize before their parent
+            }
+            block.status = BlockStatus::Finalized;
+            println!("Block #{} finalized", number);
+            self.events.push(BlockEvent::Finalized(number));
+            self.ledger.refund(BondId::Block(number));
+        }
+    }
+
+    /// Root the block committed to at `step`: the state after `tx[step - 1]`,
+    /// or for step 0 its pre-state root.
+    fn root_at_step(&self, block_number: BlockNumber, step: usize) -> Option<Hash> {
+        let block = &self.blocks[block_number as usize];
+        match step {
+            0 => Some(block.pre_state_root),
+            _ => block.state_roots.get(step - 1).copied(),
+        }
+    }
+}
+
+fn check_caller(caller: Address, account: Address) -> Result<(), VerifierError> {
+    if caller != account {
+        return Err(VerifierError::UnauthorizedCaller { caller, account });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::transaction_leaf;
+    use crate::ledger::{Bond, PayoutKind};
+    use crate::merkle::{MerkleProof, hash_node};
+    use crate::signature::{EMPTY_AGGREGATE, aggregate_signatures};
+    use crate::state::{NATIVE_ASSET, State, Transaction, TxError, TxKind};
+    use crate::test_utils::{COINBASE, CONTEXT, addr, key, setup_state, transfer};
+
+    // Applies `txs` on top of `pre_state` the way an honest sequencer
+    // extending `parent` (genesis if none) would
+    fn build_block(
+        parent: Option<&RollupBlock>,
+        pre_state: &State,
+        txs: Vec<SignedTransaction>,
+    ) -> RollupBlock {
+        let mut state = pre_state.clone();
+        let mut state_roots = vec![];
+        let mut receipts = vec![];
+        for tx in &txs {
+            receipts.push(Receipt::from(state.apply_signed_tx(tx, &CONTEXT)));
+            state_roots.push(state.root());
+        }
+        RollupBlock {
+            block_number: parent.map_or(0, |parent| parent.block_number + 1),
+            parent_hash: parent.map_or(GENESIS_PARENT_HASH, RollupBlock::hash),
+            sequencer: 10,
+            coinbase: COINBASE,
+            base_fee: 0,
+            timestamp: 0,
+            pre_state_root: pre_state.root(),
+            aggregate_signature: aggregate_signatures(&txs).unwrap(),
+            transactions: txs,
+            state_roots,
+            receipts,
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        }
+    }
+
+    // What an honest challenger replaying L2 submits against tx[tx_index]
+    fn prove_step(block: &RollupBlock, tx_index: usize, pre_state: &State) -> FraudProof {
+        let mut state = pre_state.clone();
+        for tx in &block.transactions[..tx_index] {
+            let _ = state.apply_signed_tx(tx, &CONTEXT);
+        }
+        FraudProof::build(
+            &state,
+            &block.transactions,
+            &block.context(),
+            &block.state_roots,
+            &block.receipts,
+            tx_index,
+        )
+    }
+
+    #[test]
+    fn test_valid_transaction_block() {
+        let mut state = State::new();
+        state.balances.insert((addr(1), NATIVE_ASSET), 100);
+        state.balances.insert((addr(2), NATIVE_ASSET), 50);
+
+        let mut l1 = L1Verifier::new(5, state.root()); // timeout = 5 ticks
+
+        // This will be used by both the block and L1 verifier
+        let tx = transfer(1, 2, 40, 0);
+
+        // Simulate how L2 would compute post-state
+        let mut post_state = state.clone();
+        assert!(post_state.apply_signed_tx(&tx, &CONTEXT).is_ok()); // ensure tx is valid
+
+        // Construct block from same state
+        let block = RollupBlock {
+            block_number: 0,
+            parent_hash: GENESIS_PARENT_HASH,
+            sequencer: 10,
+            coinbase: COINBASE,
+            base_fee: 0,
+            timestamp: 0,
+            pre_state_root: state.root(),
+            transactions: vec![tx.clone()],
+            aggregate_signature: aggregate_signatures(std::slice::from_ref(&tx)).unwrap(),
+            state_roots: vec![post_state.root()],
+            receipts: vec![Receipt::Success],
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        };
+
+        l1.submit_block(10, block).unwrap();
+
+        // Submit challenge even though it's a valid tx
+        let fraud_challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: FraudProof::build(
+                &state,
+                std::slice::from_ref(&tx),
+                &CONTEXT,
+                &[post_state.root()],
+                &[Receipt::Success],
+                0,
+            ),
+            valid: None,
+        };
+
+        l1.submit_challenge(99, fraud_challenge).unwrap();
+        l1.advance_time(6); // trigger fraud check
+
+        // ✅ Should be resolved as valid
+        assert_eq!(l1.resolved_challenges.len(), 1);
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_invalid_transaction_detected() {
+        let state = setup_state();
+        let mut l1 = L1Verifier::new(5, state.root());
+
+        let tx = transfer(1, 2, 1000, 0); // Invalid tx
+        let mut post_state = state.clone();
+        let _ = post_state.apply_signed_tx(&tx, &CONTEXT); // Still applies in mock rollup
+
+        let block = RollupBlock {
+            block_number: 0,
+            parent_hash: GENESIS_PARENT_HASH,
+            sequencer: 10,
+            coinbase: COINBASE,
+            base_fee: 0,
+            timestamp: 0,
+            pre_state_root: state.root(),
+            transactions: vec![tx.clone()],
+            aggregate_signature: aggregate_signatures(std::slice::from_ref(&tx)).unwrap(),
+            state_roots: vec![post_state.root()],
+            receipts: vec![Receipt::Success],
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        };
+
+        l1.submit_block(10, block).unwrap();
+
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: FraudProof::build(
+                &state,
+                std::slice::from_ref(&tx),
+                &CONTEXT,
+                &[post_state.root()],
+                &[Receipt::Success],
+                0,
+            ),
+            valid: None,
+        };
+
+        l1.submit_challenge(99, challenge).unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(false));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_challenge_before_timeout_not_processed() {
+        let state = setup_state();
+        let mut l1 = L1Verifier::new(10, state.root());
+
+        let tx = transfer(1, 2, 10, 0);
+        let mut post_state = state.clone();
+        let _ = post_state.apply_signed_tx(&tx, &CONTEXT);
+
+        let block = RollupBlock {
+            block_number: 0,
+            parent_hash: GENESIS_PARENT_HASH,
+            sequencer: 10,
+            coinbase: COINBASE,
+            base_fee: 0,
+            timestamp: 0,
+            pre_state_root: state.root(),
+            transactions: vec![tx.clone()],
+            aggregate_signature: aggregate_signatures(std::slice::from_ref(&tx)).unwrap(),
+            state_roots: vec![post_state.root()],
+            receipts: vec![Receipt::Success],
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        };
+
+        l1.submit_block(10, block).unwrap();
+
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 77,
+            time: l1.time,
+            proof: FraudProof::build(
+                &state,
+                std::slice::from_ref(&tx),
+                &CONTEXT,
+                &[post_state.root()],
+                &[Receipt::Success],
+                0,
+            ),
+            valid: None,
+        };
+
+        l1.submit_challenge(77, challenge).unwrap();
+        l1.advance_time(5); // not enough to trigger timeout
+
+        assert!(l1.resolved_challenges.is_empty());
+    }
+
+    #[test]
+    fn test_first_tx_is_checked_against_genesis_root() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let tx = transfer(1, 3, 1000, 0);
+        let mut block = build_block(None, &genesis, vec![tx.clone()]);
+        block.receipts = vec![Receipt::Success]; // claims the overdraft went through
+
+        // Witnesses from a made-up pre-state where the sender could pay
+        let mut fake_genesis = genesis.clone();
+        fake_genesis.balances.insert((addr(1), NATIVE_ASSET), 1100);
+        let mut fake_post = fake_genesis.clone();
+        let _ = fake_post.apply_signed_tx(&tx, &CONTEXT);
+        let mut fake_proof = FraudProof::build(
+            &fake_genesis,
+            std::slice::from_ref(&tx),
+            &CONTEXT,
+            &block.state_roots,
+            &[Receipt::Success],
+            0,
+        );
+        fake_proof.post_state_root = fake_post.root();
+
+        l1.submit_block(10, block.clone()).unwrap();
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: fake_proof,
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.submit_challenge(
+            98,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 98,
+                time: l1.time,
+                proof: prove_step(&block, 0, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        // Only the proof against the committed genesis counts
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.resolved_challenges[1].valid, Some(false));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_valid_block_after_block_with_failed_tx() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let failed_tx = transfer(1, 3, 1000, 0);
+        let mut state = genesis.clone();
+        let _ = state.apply_signed_tx(&failed_tx, &CONTEXT);
+        l1.submit_block(
+            10,
+            RollupBlock {
+                block_number: 0,
+                parent_hash: GENESIS_PARENT_HASH,
+                sequencer: 10,
+                coinbase: COINBASE,
+                base_fee: 0,
+                timestamp: 0,
+                pre_state_root: genesis.root(),
+                aggregate_signature: aggregate_signatures(std::slice::from_ref(&failed_tx))
+                    .unwrap(),
+                transactions: vec![failed_tx],
+                state_roots: vec![state.root()],
+                receipts: vec![Receipt::Failed(TxError::InsufficientBalance)],
+                submitted_at: 0,
+                status: BlockStatus::Pending,
+            },
+        )
+        .unwrap();
+        let pre_state = state.clone();
+
+        let tx = transfer(1, 2, 40, 0);
+        assert!(state.apply_signed_tx(&tx, &CONTEXT).is_ok());
+        l1.submit_block(
+            10,
+            RollupBlock {
+                block_number: 1,
+                parent_hash: l1.tip_hash(),
+                sequencer: 10,
+                coinbase: COINBASE,
+                base_fee: 0,
+                timestamp: 0,
+                pre_state_root: pre_state.root(),
+                transactions: vec![tx.clone()],
+                aggregate_signature: aggregate_signatures(std::slice::from_ref(&tx)).unwrap(),
+                state_roots: vec![state.root()],
+                receipts: vec![Receipt::Success],
+                submitted_at: 0,
+                status: BlockStatus::Pending,
+            },
+        )
+        .unwrap();
+
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 1,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: FraudProof::build(
+                    &pre_state,
+                    std::slice::from_ref(&tx),
+                    &CONTEXT,
+                    &[state.root()],
+                    &[Receipt::Success],
+                    0,
+                ),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        // Honest block #1 must not be flagged because of block #0's failed tx
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[1].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_challenge_checks_only_disputed_tx_in_multi_tx_block() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let txs = vec![
+            transfer(1, 2, 40, 0),
+            transfer(2, 3, 30, 0),
+            transfer(1, 3, 10, 1),
+        ];
+        let block = build_block(None, &genesis, txs);
+        l1.submit_block(10, block.clone()).unwrap();
+
+        for tx_index in 0..3 {
+            l1.submit_challenge(
+                99,
+                FraudChallenge {
+                    block_number: 0,
+                    tx_index,
+                    challenger: 99,
+                    time: l1.time,
+                    proof: prove_step(&block, tx_index, &genesis),
+                    valid: None,
+                },
+            )
+            .unwrap();
+        }
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges.len(), 3);
+        assert!(l1.resolved_challenges.iter().all(|c| c.valid == Some(true)));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_fraud_in_later_tx_is_pinned_to_that_step() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let txs = vec![transfer(1, 2, 40, 0), transfer(2, 3, 30, 0)];
+        let mut block = build_block(None, &genesis, txs.clone());
+        // Sequencer mints 500 for itself while "applying" tx[1]
+        let mut forged = genesis.clone();
+        for tx in &txs {
+            let _ = forged.apply_signed_tx(tx, &CONTEXT);
+        }
+        forged.balances.insert((addr(7), NATIVE_ASSET), 500);
+        block.state_roots[1] = forged.root();
+        l1.submit_block(10, block.clone()).unwrap();
+
+        for tx_index in 0..2 {
+            l1.submit_challenge(
+                99,
+                FraudChallenge {
+                    block_number: 0,
+                    tx_index,
+                    challenger: 99,
+                    time: l1.time,
+                    proof: prove_step(&block, tx_index, &genesis),
+                    valid: None,
+                },
+            )
+            .unwrap();
+        }
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.resolved_challenges[1].valid, Some(false));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_forged_witness_does_not_revert_honest_block() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let tx = transfer(2, 1, 50, 0);
+        let block = build_block(None, &genesis, vec![tx]);
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Challenger pretends the sender was broke to make the tx look like it failed
+        let mut proof = prove_step(&block, 0, &genesis);
+        proof.from_witness.balances[0].balance = 0;
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof,
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_replayed_transaction_is_proven_fraud() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let tx = transfer(1, 2, 40, 0);
+        // Sequencer includes the transfer twice and debits the sender twice
+        let mut block = build_block(None, &genesis, vec![tx.clone(), tx]);
+        let mut replayed = genesis.clone();
+        replayed.balances.insert((addr(1), NATIVE_ASSET), 20);
+        replayed.balances.insert((addr(2), NATIVE_ASSET), 130);
+        replayed.nonces.insert(addr(1), 2);
+        block.state_roots[1] = replayed.root();
+        l1.submit_block(10, block.clone()).unwrap();
+
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 1,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block, 1, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(false));
+        assert!(l1.blocks.is_empty());
+    }
+
+    #[test]
+    fn test_challenges_are_judged_on_committed_transactions() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let block = build_block(None, &genesis, vec![transfer(1, 2, 40, 0)]);
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Replaying an overdraft in place of tx[0] would expose its success receipt,
+        // but the overdraft isn't what the block's transactions root commits to
+        let swapped = [transfer(1, 2, 400, 0)];
+        let proof = FraudProof::build(
+            &genesis,
+            &swapped,
+            &CONTEXT,
+            &block.state_roots,
+            &block.receipts,
+            0,
+        );
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+        assert!(!proof.proves_inclusion(&block.header(), 0));
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof,
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_inclusion_proofs_must_span_the_whole_block() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let txs = vec![
+            transfer(1, 2, 40, 0),
+            transfer(2, 1, 10, 0),
+            transfer(1, 2, 5, 1),
+        ];
+        let block = build_block(None, &genesis, txs.clone());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Replaying tx[2] in place of tx[1] changes the root, and a two-leaf
+        // proof with H(tx[0], tx[1]) as the sibling places tx[2] at index 1
+        let mut after_tx0 = genesis.clone();
+        after_tx0.apply_signed_tx(&txs[0], &CONTEXT).unwrap();
+        let mut proof = FraudProof::build(
+            &after_tx0,
+            &[txs[0].clone(), txs[2].clone()],
+            &CONTEXT,
+            &block.state_roots,
+            &block.receipts[..2],
+            1,
+        );
+        let tx_leaves: Vec<_> = txs.iter().map(transaction_leaf).collect();
+        proof.tx_proof = MerkleProof {
+            index: 1,
+            leaf_count: 2,
+            siblings: vec![hash_node(&tx_leaves[0], &tx_leaves[1])],
+        };
+        let receipt_leaves: Vec<_> = block.receipts.iter().map(Receipt::leaf).collect();
+        proof.receipt_proof = MerkleProof {
+            index: 1,
+            leaf_count: 2,
+            siblings: vec![hash_node(&receipt_leaves[0], &receipt_leaves[1])],
+        };
+        let header = block.header();
+        assert_eq!(proof.verify(&CONTEXT), Verdict::Fraud);
+        assert!(
+            proof
+                .tx_proof
+                .verify(&header.transactions_root, &tx_leaves[2])
+        );
+        assert!(proof.proves_receipt(&header.receipts_root, 1));
+        assert!(!proof.proves_inclusion(&header, 1));
+
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 1,
+                challenger: 99,
+                time: l1.time,
+                proof,
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_block_hash_commits_to_every_intermediate_root() {
+        let genesis = setup_state();
+        let block = build_block(None, &genesis, four_txs());
+        let mut altered = block.clone();
+        altered.state_roots[1] = [9; 32];
+        assert_ne!(altered.hash(), block.hash());
+        assert_eq!(altered.post_state_root(), block.post_state_root());
+
+        // Step proofs are checked against the roots the header commits to
+        let proof = prove_step(&block, 2, &genesis);
+        assert!(proof.proves_inclusion(&block.header(), 2));
+        assert!(!proof.proves_inclusion(&altered.header(), 2));
+        let mut wrong_post = proof.clone();
+        wrong_post.post_state_root = [9; 32];
+        assert!(!wrong_post.proves_inclusion(&block.header(), 2));
+        let mut no_pre_proof = proof;
+        no_pre_proof.pre_state_proof = None;
+        assert!(!no_pre_proof.proves_inclusion(&block.header(), 2));
+    }
+
+    #[test]
+    fn test_challenges_are_judged_on_committed_receipts() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        // Second transfer overdraws, so the honest block records it as failed
+        let block = build_block(
+            None,
+            &genesis,
+            vec![transfer(1, 2, 40, 0), transfer(1, 2, 90, 1)],
+        );
+        assert_eq!(
+            block.receipts,
+            [
+                Receipt::Success,
+                Receipt::Failed(TxError::InsufficientBalance)
+            ]
+        );
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Replaying the step agrees with the block
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 1,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block, 1, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+
+        // Pinning a made-up success receipt on the block doesn't prove against its root
+        let mut made_up = prove_step(&block, 1, &genesis);
+        made_up.receipt = Receipt::Success;
+        assert_eq!(made_up.verify(&CONTEXT), Verdict::Fraud);
+        l1.submit_challenge(
+            98,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 1,
+                challenger: 98,
+                time: l1.time,
+                proof: made_up,
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.resolved_challenges[1].valid, Some(true));
+        assert_eq!(l1.blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_failed_tx_policy_decides_challenges() {
+        let genesis = setup_state();
+        // Second transfer overdraws and is recorded as failed
+        let block = build_block(
+            None,
+            &genesis,
+            vec![transfer(1, 2, 40, 0), transfer(1, 2, 90, 1)],
+        );
+
+        for (policy, failed_tx_allowed) in [
+            (FailedTxPolicy::Record, true),
+            (FailedTxPolicy::Reject, false),
+        ] {
+            let mut l1 = L1Verifier::new(5, genesis.root()).with_failed_tx_policy(policy);
+            l1.submit_block(10, block.clone()).unwrap();
+            for (tx_index, challenger) in [(0, 99), (1, 98)] {
+                l1.submit_challenge(
+                    challenger,
+                    FraudChallenge {
+                        block_number: 0,
+                        tx_index,
+                        challenger,
+                        time: l1.time,
+                        proof: prove_step(&block, tx_index, &genesis),
+                        valid: None,
+                    },
+                )
+                .unwrap();
+            }
+            l1.advance_time(6);
+
+            // The successful transfer is fine either way
+            assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+            assert_eq!(l1.resolved_challenges[1].valid, Some(failed_tx_allowed));
+            assert_eq!(l1.blocks.len(), failed_tx_allowed as usize);
+        }
+    }
+
+    #[test]
+    fn test_rejecting_policy_settles_disputes_too() {
+        let genesis = setup_state();
+        let mut l1 =
+            L1Verifier::new(5, genesis.root()).with_failed_tx_policy(FailedTxPolicy::Reject);
+        let block = build_block(None, &genesis, vec![transfer(1, 2, 900, 0)]);
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // The proposer's own proof shows the transaction failed
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert!(l1.defend_step(id, 10, &prove_step(&block, 0, &genesis)));
+        assert_eq!(l1.dispute(id).unwrap().winner, Some(Party::Challenger));
+        assert!(l1.blocks.is_empty());
+    }
+
+    #[test]
+    fn test_block_with_forged_signature_is_rejected() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        // Sequencer drains account 1 into its own account using 1's old signature
+        let mut forged = transfer(1, 2, 10, 0);
+        forged.tx.to = addr(10);
+        forged.tx.amount = 100;
+        let block = build_block(None, &genesis, vec![transfer(2, 1, 5, 0), forged]);
+
+        assert_eq!(
+            l1.submit_block(10, block),
+            Err(VerifierError::InvalidSignature {
+                block_number: 0,
+                tx_index: 1
+            })
+        );
+        assert!(l1.blocks.is_empty());
+    }
+
+    #[test]
+    fn test_bad_aggregate_falls_back_to_per_tx_signatures() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        // Every transaction is signed; only the block's aggregate is wrong
+        let mut block = build_block(None, &genesis, four_txs());
+        block.aggregate_signature = EMPTY_AGGREGATE;
+        l1.submit_block(10, block).unwrap();
+        assert_eq!(l1.next_block_number(), 1);
+    }
+
+    #[test]
+    fn test_fees_are_checked_against_block_coinbase() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+
+        let tx = key(1).sign(Transaction {
+            kind: TxKind::Transfer,
+            from: addr(1),
+            to: addr(2),
+            asset: NATIVE_ASSET,
+            amount: 40,
+            fee: 2,
+            nonce: 0,
+        });
+        let block = build_block(None, &genesis, vec![tx.clone()]);
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Replaying with some other fee recipient doesn't reach the block's root
+        let mut elsewhere = genesis.clone();
+        let elsewhere_context = BlockContext {
+            coinbase: addr(2),
+            ..CONTEXT
+        };
+        let _ = elsewhere.apply_signed_tx(&tx, &elsewhere_context);
+        assert_ne!(elsewhere.root(), block.state_roots[0]);
+
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: FraudProof::build(
+                    &genesis,
+                    std::slice::from_ref(&tx),
+                    &elsewhere_context,
+                    &block.state_roots,
+                    &[Receipt::Success],
+                    0,
+                ),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        // The proof witnessed the wrong coinbase, so the block stands
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_base_fee_follows_parent_fullness() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root()).with_fee_market(FeeMarket {
+            initial_base_fee: 8,
+            target_txs: 2,
+        });
+        let mut block = build_block(None, &genesis, four_txs());
+
+        assert_eq!(
+            l1.submit_block(10, block.clone()),
+            Err(VerifierError::WrongBaseFee {
+                block_number: 0,
+                expected: 8,
+                got: 0
+            })
+        );
+        block.base_fee = 8;
+        let mut too_large = block.clone();
+        too_large.transactions.push(transfer(2, 1, 1, 1));
+        too_large.state_roots.push(genesis.root());
+        too_large.receipts.push(Receipt::Success);
+        assert_eq!(
+            l1.submit_block(10, too_large),
+            Err(VerifierError::BlockTooLarge {
+                block_number: 0,
+                tx_count: 5,
+                max: 4
+            })
+        );
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // A full parent raises the fee by an eighth, an empty one lowers it
+        assert_eq!(l1.next_base_fee(), 9);
+        let mut tip_state = genesis.clone();
+        for tx in &block.transactions {
+            let _ = tip_state.apply_signed_tx(tx, &CONTEXT);
+        }
+        let mut empty = build_block(Some(&block), &tip_state, vec![]);
+        empty.base_fee = 9;
+        l1.submit_block(10, empty).unwrap();
+        assert_eq!(l1.next_base_fee(), 8);
+
+        // None of the transactions paid the base fee, so applying them at all was fraud
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block, 0, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+        assert_eq!(l1.resolved_challenges[0].valid, Some(false));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.next_base_fee(), 8);
+    }
+
+    fn four_txs() -> Vec<SignedTransaction> {
+        vec![
+            transfer(1, 2, 40, 0),
+            transfer(2, 3, 30, 0),
+            transfer(3, 4, 20, 0),
+            transfer(1, 4, 10, 1),
+        ]
+    }
+
+    // Drives bisection to a single step; the proposer restates the block's roots
+    // and the challenger agrees whenever they match its own replay
+    fn bisect_to_single_step(l1: &mut L1Verifier, id: usize, honest_block: &RollupBlock) -> usize {
+        loop {
+            let dispute = l1.dispute(id).unwrap();
+            if let Some(tx_index) = dispute.disputed_tx() {
+                return tx_index;
+            }
+            let midpoint = dispute.midpoint();
+            let block_root = l1.blocks[dispute.block_number as usize].state_roots[midpoint - 1];
+            assert!(l1.bisect_dispute(id, 10, block_root));
+            let agrees = block_root == honest_block.state_roots[midpoint - 1];
+            let challenger = l1.dispute(id).unwrap().challenger;
+            assert!(l1.respond_to_bisection(id, challenger, agrees));
+        }
+    }
+
+    #[test]
+    fn test_dispute_on_honest_block_is_won_by_proposer() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // A griefing challenger disagrees with everything
+        let id = l1.open_dispute(0, 99).unwrap();
+        loop {
+            let dispute = l1.dispute(id).unwrap();
+            if dispute.disputed_tx().is_some() {
+                break;
+            }
+            assert!(l1.bisect_dispute(id, 10, block.state_roots[dispute.midpoint() - 1]));
+            assert!(l1.respond_to_bisection(id, 99, false));
+        }
+        assert_eq!(l1.dispute(id).unwrap().disputed_tx(), Some(0));
+
+        assert!(l1.defend_step(id, 10, &prove_step(&block, 0, &genesis)));
+        assert_eq!(l1.dispute(id).unwrap().winner, Some(Party::Proposer));
+        assert_eq!(l1.blocks[0].status, BlockStatus::Pending);
+    }
+
+    #[test]
+    fn test_dispute_narrows_to_fraudulent_step() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let txs = four_txs();
+        let honest_block = build_block(None, &genesis, txs.clone());
+
+        // Sequencer inflates account 7 while applying tx[2]; later roots build on it
+        let mut state = genesis.clone();
+        let mut state_roots = vec![];
+        for (i, tx) in txs.iter().enumerate() {
+            let _ = state.apply_signed_tx(tx, &CONTEXT);
+            if i == 2 {
+                state.balances.insert((addr(7), NATIVE_ASSET), 500);
+            }
+            state_roots.push(state.root());
+        }
+        let block = RollupBlock {
+            block_number: 0,
+            parent_hash: GENESIS_PARENT_HASH,
+            sequencer: 10,
+            coinbase: COINBASE,
+            base_fee: 0,
+            timestamp: 0,
+            pre_state_root: genesis.root(),
+            aggregate_signature: aggregate_signatures(&txs).unwrap(),
+            transactions: txs,
+            state_roots,
+            receipts: honest_block.receipts.clone(),
+            submitted_at: 0,
+            status: BlockStatus::Pending,
+        };
+        l1.submit_block(10, block.clone()).unwrap();
+
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert_eq!(bisect_to_single_step(&mut l1, id, &honest_block), 2);
+
+        // The best the proposer can do is prove the real transition, which exposes it
+        let mut pre_state = genesis.clone();
+        for tx in &block.transactions[..2] {
+            let _ = pre_state.apply_signed_tx(tx, &CONTEXT);
+        }
+        let proof = FraudProof::build(
+            &pre_state,
+            &block.transactions,
+            &block.context(),
+            &block.state_roots,
+            &block.receipts,
+            2,
+        );
+        assert!(l1.defend_step(id, 10, &proof));
+        assert_eq!(l1.dispute(id).unwrap().winner, Some(Party::Challenger));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_dispute_loser_is_whoever_misses_their_deadline() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // Challenger goes silent after the first bisection
+        let silent_challenger = l1.open_dispute(0, 99).unwrap();
+        assert!(l1.bisect_dispute(silent_challenger, 10, block.state_roots[1]));
+
+        // Proposer never answers this one
+        let silent_proposer = l1.open_dispute(0, 98).unwrap();
+
+        l1.advance_time(5); // deadlines are inclusive
+        assert!(l1.dispute(silent_challenger).unwrap().is_active());
+        l1.advance_time(1);
+
+        assert_eq!(
+            l1.dispute(silent_challenger).unwrap().winner,
+            Some(Party::Proposer)
+        );
+        assert_eq!(
+            l1.dispute(silent_proposer).unwrap().winner,
+            Some(Party::Challenger)
+        );
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_dispute_equivocating_proposer_loses() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        l1.submit_block(10, build_block(None, &genesis, four_txs()))
+            .unwrap();
+
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert!(!l1.bisect_dispute(id, 10, genesis.root()));
+
+        assert_eq!(l1.dispute(id).unwrap().winner, Some(Party::Challenger));
+        assert!(l1.blocks.is_empty());
+        assert_eq!(l1.reverted_blocks[0].status, BlockStatus::Reverted);
+    }
+
+    #[test]
+    fn test_only_the_sequencer_moves_for_the_proposer() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // The challenger equivocating in the sequencer's name settles nothing
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert!(!l1.bisect_dispute(id, 99, [0xAB; 32]));
+        assert!(l1.dispute(id).unwrap().is_active());
+        assert_eq!(l1.blocks[0].status, BlockStatus::Pending);
+        assert_eq!(l1.ledger().balance(10), 0); // both still bonded
+        assert_eq!(l1.ledger().balance(99), 0);
+
+        assert!(l1.bisect_dispute(id, 10, block.state_roots[1]));
+        assert!(l1.respond_to_bisection(id, 99, true));
+        assert!(l1.bisect_dispute(id, 10, block.state_roots[2]));
+        assert!(l1.respond_to_bisection(id, 99, false));
+        let proof = prove_step(&block, 2, &genesis);
+        assert!(!l1.defend_step(id, 99, &proof));
+        assert!(l1.dispute(id).unwrap().is_active());
+        assert!(l1.defend_step(id, 10, &proof));
+        assert_eq!(l1.dispute(id).unwrap().winner, Some(Party::Proposer));
+    }
+
+    #[test]
+    fn test_dispute_rejects_out_of_turn_moves() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+        let mut tip_state = genesis.clone();
+        for tx in &block.transactions {
+            let _ = tip_state.apply_signed_tx(tx, &CONTEXT);
+        }
+        l1.submit_block(10, build_block(Some(&block), &tip_state, vec![]))
+            .unwrap();
+
+        assert_eq!(l1.open_dispute(1, 99), Err(VerifierError::EmptyBlock(1)));
+        assert_eq!(l1.open_dispute(2, 99), Err(VerifierError::UnknownBlock(2)));
+
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert!(!l1.respond_to_bisection(id, 99, true)); // proposer moves first
+        assert!(!l1.defend_step(id, 10, &prove_step(&block, 0, &genesis))); // still bisecting
+        assert!(l1.bisect_dispute(id, 10, block.state_roots[1]));
+        assert!(!l1.bisect_dispute(id, 10, block.state_roots[1])); // challenger's turn
+        assert!(!l1.respond_to_bisection(id, 98, false)); // not the challenger
+        assert!(l1.respond_to_bisection(id, 99, false));
+        assert!(l1.bisect_dispute(id, 10, block.state_roots[0]));
+        assert!(l1.respond_to_bisection(id, 99, false));
+
+        // Proof for the wrong step, then a forged witness: both rejected, game still open
+        assert!(!l1.defend_step(id, 10, &prove_step(&block, 1, &genesis)));
+        let mut forged = prove_step(&block, 0, &genesis);
+        forged.to_witness.balances[0].balance += 1;
+        assert!(!l1.defend_step(id, 10, &forged));
+        assert!(l1.dispute(id).unwrap().is_active());
+    }
+
+    fn bonded_verifier(genesis: &State) -> L1Verifier {
+        let mut l1 = L1Verifier::new(5, genesis.root()).with_bonds(100, 10);
+        l1.deposit(10, 100).unwrap(); // sequencer
+        l1.deposit(99, 10).unwrap(); // challenger
+        l1
+    }
+
+    #[test]
+    fn test_block_and_challenge_need_a_bond() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root()).with_bonds(100, 10);
+        let block = build_block(None, &genesis, four_txs());
+
+        assert_eq!(
+            l1.submit_block(10, block.clone()),
+            Err(VerifierError::InsufficientBond {
+                owner: 10,
+                required: 100
+            })
+        );
+        assert!(l1.blocks.is_empty());
+
+        l1.deposit(10, 150).unwrap();
+        assert_eq!(
+            l1.deposit(10, Balance::MAX),
+            Err(VerifierError::BalanceOverflow(10))
+        );
+        l1.submit_block(10, block.clone()).unwrap();
+        assert_eq!(l1.ledger().balance(10), 50);
+        assert_eq!(
+            l1.ledger().bond(BondId::Block(0)),
+            Some(Bond {
+                owner: 10,
+                amount: 100
+            })
+        );
+
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: prove_step(&block, 0, &genesis),
+            valid: None,
+        };
+        let no_bond = VerifierError::InsufficientBond {
+            owner: 99,
+            required: 10,
+        };
+        assert_eq!(l1.submit_challenge(99, challenge.clone()), Err(no_bond));
+        assert_eq!(l1.open_dispute(0, 99), Err(no_bond));
+        l1.deposit(99, 10).unwrap();
+        l1.submit_challenge(99, challenge).unwrap();
+        assert_eq!(l1.ledger().balance(99), 0);
+    }
+
+    #[test]
+    fn test_bonds_are_only_locked_from_the_caller() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        let block = build_block(None, &genesis, four_txs());
+
+        // Nobody can bond a block or challenge in someone else's name
+        assert_eq!(
+            l1.submit_block(99, block.clone()),
+            Err(VerifierError::UnauthorizedCaller {
+                caller: 99,
+                account: 10
+            })
+        );
+        l1.submit_block(10, block.clone()).unwrap();
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: prove_step(&block, 0, &genesis),
+            valid: None,
+        };
+        assert_eq!(
+            l1.submit_challenge(10, challenge.clone()),
+            Err(VerifierError::UnauthorizedCaller {
+                caller: 10,
+                account: 99
+            })
+        );
+        assert_eq!(l1.ledger().balance(99), 10);
+        l1.submit_challenge(99, challenge).unwrap();
+        assert_eq!(l1.ledger().balance(99), 0);
+    }
+
+    #[test]
+    fn test_proven_fraud_slashes_sequencer_and_rewards_challenger() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+
+        let mut block = build_block(None, &genesis, four_txs());
+        block.state_roots[0] = genesis.root(); // skips tx[0]
+        l1.submit_block(10, block.clone()).unwrap();
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block, 0, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(false));
+        assert_eq!(l1.ledger().bond(BondId::Block(0)), None);
+        assert_eq!(l1.ledger().balance(10), 0);
+        assert_eq!(l1.ledger().balance(99), 60); // bond back + half the block bond
+        assert_eq!(l1.ledger().burned(), 50);
+        let kinds: Vec<_> = l1
+            .ledger()
+            .payouts()
+            .iter()
+            .map(|p| (p.to, p.kind))
+            .collect();
+        assert_eq!(kinds, [(99, PayoutKind::Reward), (99, PayoutKind::Refund)]);
+    }
+
+    #[test]
+    fn test_failed_challenge_slashes_challenger() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 0,
+                tx_index: 1,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block, 1, &genesis),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[0].valid, Some(true));
+        assert_eq!(l1.ledger().balance(99), 0);
+        assert_eq!(l1.ledger().burned(), 5);
+        // Reward plus the block bond, returned once the block finalized
+        assert_eq!(l1.ledger().balance(10), 105);
+        assert_eq!(l1.ledger().bond(BondId::Block(0)), None);
+    }
+
+    #[test]
+    fn test_dispute_outcome_settles_bonds() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        l1.deposit(98, 10).unwrap();
+
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // 99 goes silent after the first bisection, so it loses
+        let lost = l1.open_dispute(0, 99).unwrap();
+        assert!(l1.bisect_dispute(lost, 10, block.state_roots[1]));
+        l1.advance_time(3);
+        // 98 wins because the sequencer stops answering it
+        let won = l1.open_dispute(0, 98).unwrap();
+
+        l1.advance_time(3);
+        assert_eq!(l1.dispute(lost).unwrap().winner, Some(Party::Proposer));
+        assert_eq!(l1.ledger().balance(99), 0);
+        assert_eq!(l1.ledger().balance(10), 5);
+
+        l1.advance_time(3);
+        assert_eq!(l1.dispute(won).unwrap().winner, Some(Party::Challenger));
+        assert_eq!(l1.ledger().balance(98), 60);
+        assert_eq!(l1.ledger().burned(), 55);
+    }
+
+    #[test]
+    fn test_fraud_reverts_descendants_and_chain_resumes_from_valid_tip() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        l1.deposit(10, 100).unwrap();
+        l1.deposit(11, 100).unwrap(); // sequencer of block #2
+        l1.deposit(98, 10).unwrap();
+        l1.deposit(97, 10).unwrap();
+
+        let txs = four_txs();
+        let block0 = build_block(None, &genesis, txs[..2].to_vec());
+        let mut tip_state = genesis.clone();
+        for tx in &txs[..2] {
+            let _ = tip_state.apply_signed_tx(tx, &CONTEXT);
+        }
+
+        // Block #1 mints out of thin air, block #2 honestly builds on top of it
+        let mut inflated = tip_state.clone();
+        let _ = inflated.apply_signed_tx(&txs[2], &CONTEXT);
+        inflated.balances.insert((addr(7), NATIVE_ASSET), 500);
+        let mut block1 = build_block(Some(&block0), &tip_state, vec![txs[2].clone()]);
+        block1.state_roots[0] = inflated.root();
+        let mut block2 = build_block(Some(&block1), &inflated, vec![txs[3].clone()]);
+        block2.sequencer = 11;
+
+        l1.submit_block(10, block0.clone()).unwrap();
+        l1.submit_block(10, block1.clone()).unwrap();
+        l1.submit_block(11, block2.clone()).unwrap();
+        assert_eq!(l1.tip_state_root(), block2.state_roots[0]);
+
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                block_number: 1,
+                tx_index: 0,
+                challenger: 99,
+                time: l1.time,
+                proof: prove_step(&block1, 0, &tip_state),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.submit_challenge(
+            98,
+            FraudChallenge {
+                block_number: 2,
+                tx_index: 0,
+                challenger: 98,
+                time: l1.time,
+                proof: prove_step(&block2, 0, &inflated),
+                valid: None,
+            },
+        )
+        .unwrap();
+        let dispute = l1.open_dispute(2, 97).unwrap();
+        l1.advance_time(5);
+
+        // Only the challenge on #1 was resolved; everything on #2 went with it
+        assert_eq!(l1.resolved_challenges.len(), 1);
+        assert_eq!(l1.resolved_challenges[0].valid, Some(false));
+        assert!(l1.challenges.is_empty());
+        assert!(l1.dispute(dispute).unwrap().cancelled);
+        assert_eq!(l1.blocks.len(), 1);
+        let reverted: Vec<_> = l1.reverted_blocks.iter().map(|b| b.block_number).collect();
+        assert_eq!(reverted, [1, 2]);
+        assert!(
+            l1.reverted_blocks
+                .iter()
+                .all(|b| b.status == BlockStatus::Reverted)
+        );
+
+        // Fraudster slashed, everyone else made whole; #0 finalized and got its bond back
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+        assert_eq!(l1.ledger().balance(10), 100);
+        assert_eq!(l1.ledger().balance(11), 100);
+        assert_eq!(l1.ledger().balance(99), 60);
+        assert_eq!(l1.ledger().balance(98), 10);
+        assert_eq!(l1.ledger().balance(97), 10);
+
+        // Sequencer resubmits #1 on top of the valid tip
+        assert_eq!(l1.next_block_number(), 1);
+        assert_eq!(l1.tip_state_root(), tip_state.root());
+        let honest1 = build_block(Some(&block0), &tip_state, vec![txs[2].clone()]);
+        l1.submit_block(10, honest1.clone()).unwrap();
+        l1.submit_challenge(
+            98,
+            FraudChallenge {
+                block_number: 1,
+                tx_index: 0,
+                challenger: 98,
+                time: l1.time,
+                proof: prove_step(&honest1, 0, &tip_state),
+                valid: None,
+            },
+        )
+        .unwrap();
+        l1.advance_time(6);
+
+        assert_eq!(l1.resolved_challenges[1].valid, Some(true));
+        assert_eq!(l1.blocks[1].status, BlockStatus::Finalized);
+    }
+
+    #[test]
+    fn test_blocks_finalize_after_challenge_window() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        l1.deposit(10, 100).unwrap();
+        let txs = four_txs();
+        let block0 = build_block(None, &genesis, txs[..2].to_vec());
+        let mut state = genesis.clone();
+        for tx in &txs[..2] {
+            let _ = state.apply_signed_tx(tx, &CONTEXT);
+        }
+
+        l1.submit_block(10, block0.clone()).unwrap();
+        l1.advance_time(3);
+        l1.submit_block(10, build_block(Some(&block0), &state, txs[2..].to_vec()))
+            .unwrap();
+        assert_eq!(l1.blocks[1].submitted_at, 3);
+        assert!(l1.latest_finalized_block().is_none());
+
+        l1.advance_time(2);
+        assert_eq!(l1.latest_finalized_block().unwrap().block_number, 0);
+        assert_eq!(l1.blocks[1].status, BlockStatus::Pending);
+        assert_eq!(l1.ledger().balance(10), 100);
+
+        l1.advance_time(3);
+        assert_eq!(l1.latest_finalized_block().unwrap().block_number, 1);
+        assert_eq!(
+            l1.events(),
+            [BlockEvent::Finalized(0), BlockEvent::Finalized(1)]
+        );
+        assert_eq!(l1.ledger().balance(10), 200);
+    }
+
+    #[test]
+    fn test_challenges_after_window_are_rejected() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+
+        // The window is still open on its last tick, even if nothing finalizes yet
+        l1.advance_time(4);
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: prove_step(&block, 0, &genesis),
+            valid: None,
+        };
+        l1.advance_time(1);
+        assert_eq!(
+            l1.submit_challenge(99, challenge.clone()),
+            Err(VerifierError::OutsideChallengeWindow(0))
+        );
+        assert_eq!(
+            l1.open_dispute(0, 99),
+            Err(VerifierError::OutsideChallengeWindow(0))
+        );
+        assert_eq!(l1.ledger().balance(99), 10);
+        assert_eq!(l1.blocks[0].status, BlockStatus::Finalized);
+
+        assert_eq!(
+            l1.submit_challenge(
+                99,
+                FraudChallenge {
+                    block_number: 1,
+                    ..challenge
+                }
+            ),
+            Err(VerifierError::UnknownBlock(1))
+        );
+    }
+
+    #[test]
+    fn test_open_challenge_holds_back_finality_of_block_and_children() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        l1.deposit(10, 100).unwrap();
+        l1.deposit(98, 10).unwrap();
+        let txs = four_txs();
+        let block0 = build_block(None, &genesis, txs[..2].to_vec());
+        let mut state = genesis.clone();
+        for tx in &txs[..2] {
+            let _ = state.apply_signed_tx(tx, &CONTEXT);
+        }
+        l1.submit_block(10, block0.clone()).unwrap();
+        l1.submit_block(10, build_block(Some(&block0), &state, txs[2..].to_vec()))
+            .unwrap();
+
+        // A dispute raised at the end of the window keeps #0 (and so #1) pending
+        l1.advance_time(4);
+        let id = l1.open_dispute(0, 98).unwrap();
+        l1.advance_time(3);
+        assert!(l1.latest_finalized_block().is_none());
+        assert!(l1.events().is_empty());
+
+        assert!(l1.bisect_dispute(id, 10, block0.state_roots[0]));
+        assert!(l1.respond_to_bisection(id, 98, true));
+        assert!(l1.defend_step(id, 10, &prove_step(&block0, 1, &genesis)));
+        l1.advance_time(1);
+        assert_eq!(l1.latest_finalized_block().unwrap().block_number, 1);
+    }
+
+    #[test]
+    fn test_malformed_submissions_are_rejected() {
+        let genesis = setup_state();
+        let mut l1 = bonded_verifier(&genesis);
+        let block = build_block(None, &genesis, four_txs());
+
+        assert_eq!(
+            l1.submit_block(10, build_block(Some(&block), &genesis, vec![])),
+            Err(VerifierError::NonSequentialBlock {
+                expected: 0,
+                got: 1
+            })
+        );
+        l1.submit_block(10, block.clone()).unwrap();
+        assert_eq!(
+            l1.submit_block(10, block.clone()),
+            Err(VerifierError::NonSequentialBlock {
+                expected: 1,
+                got: 0
+            })
+        );
+
+        let challenge = FraudChallenge {
+            block_number: 0,
+            tx_index: 0,
+            challenger: 99,
+            time: l1.time,
+            proof: prove_step(&block, 0, &genesis),
+            valid: None,
+        };
+        assert_eq!(
+            l1.submit_challenge(
+                99,
+                FraudChallenge {
+                    block_number: 5,
+                    ..challenge.clone()
+                }
+            ),
+            Err(VerifierError::UnknownBlock(5))
+        );
+        assert_eq!(
+            l1.submit_challenge(
+                99,
+                FraudChallenge {
+                    tx_index: 4,
+                    ..challenge.clone()
+                }
+            ),
+            Err(VerifierError::TxIndexOutOfRange {
+                block_number: 0,
+                tx_index: 4
+            })
+        );
+        // A challenge dated in the future still waits out the full delay
+        l1.submit_challenge(
+            99,
+            FraudChallenge {
+                time: 3,
+                ..challenge.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(l1.challenges[0].time, 0);
+        assert_eq!(
+            l1.submit_challenge(99, challenge),
+            Err(VerifierError::DuplicateChallenge)
+        );
+
+        // Nothing rejected ever reached the queue, so resolution can't trip on it
+        l1.advance_time(6);
+        assert_eq!(l1.resolved_challenges.len(), 1);
+        assert_eq!(l1.ledger().balance(99), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "nonzero target")]
+    fn test_fee_market_target_must_be_nonzero() {
+        let _ = L1Verifier::new(5, setup_state().root()).with_fee_market(FeeMarket {
+            initial_base_fee: 1,
+            target_txs: 0,
+        });
+    }
+
+    #[test]
+    fn test_deadlines_saturate_instead_of_overflowing() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(u64::MAX, genesis.root());
+        let block = build_block(None, &genesis, four_txs());
+        l1.submit_block(10, block.clone()).unwrap();
+        l1.advance_time(10);
+
+        let id = l1.open_dispute(0, 99).unwrap();
+        assert!(l1.bisect_dispute(id, 10, block.state_roots[1]));
+        assert!(l1.respond_to_bisection(id, 99, true));
+        assert_eq!(l1.dispute(id).unwrap().deadline, u64::MAX);
+        l1.advance_time(u64::MAX);
+        assert_eq!(l1.time, u64::MAX);
+        assert!(l1.dispute(id).unwrap().is_active()); // the end of time is still inclusive
+        assert!(l1.latest_finalized_block().is_none());
+    }
+
+    #[test]
+    fn test_blocks_must_commit_to_every_step() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let mut forged = genesis.clone();
+        forged.balances.insert((addr(7), NATIVE_ASSET), 500);
+
+        // An empty block can't slip in a post-state that no step leads to
+        let mut empty = build_block(None, &genesis, vec![]);
+        empty.state_roots.push(forged.root());
+        assert_eq!(
+            l1.submit_block(10, empty),
+            Err(VerifierError::CommitmentCountMismatch {
+                block_number: 0,
+                tx_count: 0,
+                state_roots: 1,
+                receipts: 0
+            })
+        );
+
+        let honest = build_block(None, &genesis, vec![transfer(1, 2, 40, 0)]);
+        let mut extra_root = honest.clone();
+        extra_root.state_roots.push(forged.root());
+        let mut unreceipted = honest.clone();
+        unreceipted.receipts.clear();
+        for block in [extra_root, unreceipted] {
+            assert!(matches!(
+                l1.submit_block(10, block),
+                Err(VerifierError::CommitmentCountMismatch { .. })
+            ));
+        }
+        assert_eq!(l1.tip_state_root(), genesis.root());
+        l1.submit_block(10, honest).unwrap();
+    }
+
+    #[test]
+    fn test_blocks_must_extend_the_tip() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        let txs = four_txs();
+        let block0 = build_block(None, &genesis, txs[..2].to_vec());
+        let mut state = genesis.clone();
+        for tx in &txs[..2] {
+            let _ = state.apply_signed_tx(tx, &CONTEXT);
+        }
+        assert_eq!(l1.tip_hash(), GENESIS_PARENT_HASH);
+        l1.submit_block(10, block0.clone()).unwrap();
+        assert_eq!(l1.tip_hash(), block0.hash());
+        assert_eq!(l1.blocks[0].header().post_state_root, state.root());
+
+        // A sibling of block #0 with a different receipt is a different parent
+        let mut sibling = block0.clone();
+        sibling.receipts[1] = Receipt::Failed(TxError::BadNonce);
+        assert_ne!(sibling.hash(), block0.hash());
+        assert_eq!(
+            l1.submit_block(10, build_block(Some(&sibling), &state, txs[2..].to_vec())),
+            Err(VerifierError::UnknownParent(1))
+        );
+
+        // Naming the right parent but replaying from another state
+        assert_eq!(
+            l1.submit_block(10, build_block(Some(&block0), &genesis, txs[2..].to_vec())),
+            Err(VerifierError::WrongPreStateRoot(1))
+        );
+
+        let block1 = build_block(Some(&block0), &state, txs[2..].to_vec());
+        l1.submit_block(10, block1.clone()).unwrap();
+        assert_eq!(l1.tip_hash(), block1.hash());
+        assert_eq!(block1.parent_hash, block0.hash());
+    }
+
+    #[test]
+    fn test_block_timestamps_fall_between_parent_and_now() {
+        let genesis = setup_state();
+        let mut l1 = L1Verifier::new(5, genesis.root());
+        l1.advance_time(3);
+        let mut from_the_future = build_block(None, &genesis, vec![]);
+        from_the_future.timestamp = 4;
+        assert_eq!(
+            l1.submit_block(10, from_the_future),
+            Err(VerifierError::WrongTimestamp {
+                block_number: 0,
+                timestamp: 4,
+                min: 0,
+                max: 3
+            })
+        );
+
+        let mut block0 = build_block(None, &genesis, vec![]);
+        block0.timestamp = 2;
+        l1.submit_block(10, block0.clone()).unwrap();
+        let mut before_parent = build_block(Some(&block0), &genesis, vec![]);
+        before_parent.timestamp = 1;
+        assert_eq!(
+            l1.submit_block(10, before_parent.clone()),
+            Err(VerifierError::WrongTimestamp {
+                block_number: 1,
+                timestamp: 1,
+                min: 2,
+                max: 3
+            })
+        );
+        before_parent.timestamp = 2; // same time as the parent is fine
+        l1.submit_block(10, before_parent).unwrap();
+    }
+}